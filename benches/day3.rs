@@ -0,0 +1,54 @@
+// compares counting the puzzle's five slopes one by one on the row per Vec map
+// against the bit-packed single pass, run with `cargo bench --bench day3`
+#[allow(dead_code)]
+#[path = "../src/day3.rs"]
+mod day3;
+
+use rand::SeedableRng;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// the fastest of a few runs, so a noisy neighbour doesn't skew the result
+fn fastest<F: FnMut() -> Vec<u64>>(mut f: F) -> (Duration, Vec<u64>) {
+    let mut best = Duration::MAX;
+    let mut counts = vec![];
+    for _ in 0..5 {
+        let start = Instant::now();
+        counts = black_box(f());
+        best = best.min(start.elapsed());
+    }
+
+    (best, counts)
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(2020);
+    for rows in &[10_000, 1_000_000, 4_000_000] {
+        let input = day3::generate_map(&mut rng, *rows, 31);
+
+        let map = day3::parse_map(&input)?;
+        let (vec_time, expected) = fastest(|| {
+            day3::PUZZLE_SLOPES
+                .iter()
+                .map(|s| day3::count_trees(&map, *s).unwrap())
+                .collect()
+        });
+
+        let packed = day3::PackedMap::parse(&input)?;
+        let (packed_time, counts) = fastest(|| packed.count_trees(&day3::PUZZLE_SLOPES).unwrap());
+
+        if counts != expected {
+            return Err(anyhow::anyhow!(
+                "packed counts {:?}, expected {:?}",
+                counts,
+                expected
+            ));
+        }
+        println!(
+            "{:>9} rows: vec map {:>12?}, packed {:>12?}",
+            rows, vec_time, packed_time
+        );
+    }
+
+    Ok(())
+}
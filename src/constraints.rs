@@ -0,0 +1,59 @@
+use anyhow::anyhow;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+// Repeatedly pins down keys that have a single candidate left and strikes that
+// value from every other key, until each key has exactly one value.
+pub fn resolve_unique<K, V>(
+    mut candidates: HashMap<K, HashSet<V>>,
+) -> Result<HashMap<K, V>, anyhow::Error>
+where
+    K: Clone + Eq + Hash,
+    V: Clone + Eq + Hash,
+{
+    let mut resolved = HashMap::new();
+
+    while !candidates.is_empty() {
+        let (key, value) = candidates
+            .iter()
+            .find_map(|(k, vs)| {
+                if vs.len() == 1 {
+                    vs.iter().next().map(|v| (k.clone(), v.clone()))
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| anyhow!("constraints have no unique solution"))?;
+
+        candidates.remove(&key);
+        for vs in candidates.values_mut() {
+            vs.remove(&value);
+        }
+        resolved.insert(key, value);
+    }
+
+    Ok(resolved)
+}
+
+#[test]
+fn test_resolve_unique() {
+    let mut candidates = HashMap::new();
+    candidates.insert("a", vec![1, 2, 3].into_iter().collect::<HashSet<i32>>());
+    candidates.insert("b", vec![2].into_iter().collect());
+    candidates.insert("c", vec![2, 3].into_iter().collect());
+
+    let resolved = resolve_unique(candidates).unwrap();
+
+    assert_eq!(Some(&1), resolved.get("a"));
+    assert_eq!(Some(&2), resolved.get("b"));
+    assert_eq!(Some(&3), resolved.get("c"));
+}
+
+#[test]
+fn test_resolve_ambiguous() {
+    let mut candidates = HashMap::new();
+    candidates.insert("a", vec![1, 2].into_iter().collect::<HashSet<i32>>());
+    candidates.insert("b", vec![1, 2].into_iter().collect());
+
+    assert!(resolve_unique(candidates).is_err());
+}
@@ -1,65 +1,435 @@
 use anyhow::anyhow;
+use std::str::FromStr;
 
-pub fn run() -> Result<(String, String), anyhow::Error> {
+#[derive(Debug, PartialEq)]
+pub struct KSum {
+    pub values: Vec<i64>,
+    pub positions: Vec<usize>,
+}
+
+impl KSum {
+    pub fn product(&self) -> Result<i64, anyhow::Error> {
+        self.values.iter().try_fold(1i64, |acc, v| {
+            acc.checked_mul(*v)
+                .ok_or_else(|| anyhow!("product of {:?} overflows", self.values))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pick {
+    First,
+    Largest,
+    Smallest,
+}
+
+impl FromStr for Pick {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Pick, anyhow::Error> {
+        match s {
+            "first" => Ok(Pick::First),
+            "largest" => Ok(Pick::Largest),
+            "smallest" => Ok(Pick::Smallest),
+            _ => Err(anyhow!("pick must be one of first, largest or smallest")),
+        }
+    }
+}
+
+pub fn run(
+    target: i64,
+    k: usize,
+    pick: Pick,
+    all: bool,
+) -> Result<(String, String), anyhow::Error> {
     let input: String = std::fs::read_to_string("res/day1-input")?.parse()?;
 
-    let mut input_vec: Vec<i32> = input
-        .split('\n')
-        .map(|s| s.parse::<i32>().unwrap_or(0))
-        .filter(|i| i > &0)
-        .collect();
-    input_vec.sort_unstable();
+    let input_vec = parse(&input)?;
+
+    if all {
+        for size in &[k, k + 1] {
+            for sum in k_sums(&input_vec, *size, target) {
+                println!("{:?} at {:?}", sum.values, sum.positions);
+            }
+            println!(
+                "{} combinations of {} add to {}",
+                count_k_sums(&input_vec, *size, target),
+                size,
+                target
+            );
+        }
+    }
+
+    // a part without a matching combination doesn't stop the other one
+    let answer = |size: usize| -> Result<String, anyhow::Error> {
+        Ok(match calculate(&input_vec, size, target, pick)? {
+            Some(product) => format!("{}", product),
+            None => format!("none, no {} numbers add to {}", size, target),
+        })
+    };
+
+    Ok((answer(k)?, answer(k + 1)?))
+}
+
+// every bad line is reported, one per line of the error
+fn parse(input: &str) -> Result<Vec<i64>, anyhow::Error> {
+    let mut values = vec![];
+    let mut errors = vec![];
+    for (i, line) in input.split('\n').enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.parse::<i64>() {
+            Ok(value) => values.push(value),
+            Err(e) => errors.push(format!("line {}: can't parse '{}': {}", i + 1, line, e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow!(errors.join("\n")));
+    }
+
+    Ok(values)
+}
 
-    let result_1 = calculate_part_1(&input_vec)?;
-    let result_2 = calculate_part_2(&input_vec)?;
+// None when no k numbers add to target, an error only when a product overflows
+fn calculate(
+    input: &[i64],
+    k: usize,
+    target: i64,
+    pick: Pick,
+) -> Result<Option<i64>, anyhow::Error> {
+    let product = match pick {
+        Pick::First => find_k_sum(input, k, target)
+            .map(|s| s.product())
+            .transpose()?,
+        Pick::Largest | Pick::Smallest => {
+            let mut best: Option<i64> = None;
+            for sum in k_sums(input, k, target) {
+                let product = sum.product()?;
+                best = match best {
+                    Some(b) if pick == Pick::Largest => Some(b.max(product)),
+                    Some(b) => Some(b.min(product)),
+                    None => Some(product),
+                };
+            }
+            best
+        }
+    };
 
-    Ok((format!("{}", result_1), format!("{}", result_2)))
+    Ok(product)
+}
+
+// finds k entries at distinct positions that add up to target, positions are
+// reported in input order. Sums are taken in i128 so they can't overflow.
+pub fn find_k_sum(values: &[i64], k: usize, target: i64) -> Option<KSum> {
+    let mut sorted: Vec<(usize, i64)> = values.iter().copied().enumerate().collect();
+    sorted.sort_unstable_by_key(|(_, v)| *v);
+
+    let mut chosen = vec![];
+    if !k_sum(&sorted, k, target as i128, &mut chosen) {
+        return None;
+    }
+    chosen.sort_unstable();
+
+    Some(KSum {
+        values: chosen.iter().map(|(_, v)| *v).collect(),
+        positions: chosen.iter().map(|(p, _)| *p).collect(),
+    })
+}
+
+fn k_sum(sorted: &[(usize, i64)], k: usize, target: i128, chosen: &mut Vec<(usize, i64)>) -> bool {
+    match k {
+        0 => target == 0,
+        1 => match sorted.binary_search_by_key(&target, |(_, v)| *v as i128) {
+            Ok(i) => {
+                chosen.push(sorted[i]);
+                true
+            }
+            Err(_) => false,
+        },
+        2 => {
+            if sorted.len() < 2 {
+                return false;
+            }
+            let (mut low, mut high) = (0, sorted.len() - 1);
+            while low < high {
+                let sum = sorted[low].1 as i128 + sorted[high].1 as i128;
+                match sum.cmp(&target) {
+                    std::cmp::Ordering::Equal => {
+                        chosen.push(sorted[low]);
+                        chosen.push(sorted[high]);
+                        return true;
+                    }
+                    std::cmp::Ordering::Less => low += 1,
+                    std::cmp::Ordering::Greater => high -= 1,
+                }
+            }
+            false
+        }
+        _ => {
+            for i in 0..sorted.len() {
+                chosen.push(sorted[i]);
+                if k_sum(
+                    &sorted[i + 1..],
+                    k - 1,
+                    target - sorted[i].1 as i128,
+                    chosen,
+                ) {
+                    return true;
+                }
+                chosen.pop();
+            }
+            false
+        }
+    }
 }
 
-fn calculate_part_1(input: &[i32]) -> Result<i32, anyhow::Error> {
-    for i in input {
-        for j in input {
-            let add = i + j;
+// lazily walks every combination of k distinct positions adding up to target,
+// equal values at different positions count as different combinations
+pub struct KSumIter {
+    sorted: Vec<(usize, i64)>,
+    k: usize,
+    target: i64,
+    stack: Vec<usize>,
+    cursor: usize,
+    done: bool,
+}
 
-            match add {
-                2021..=std::i32::MAX => break,
-                2020 => return Ok(i * j),
-                _ => {}
+impl KSumIter {
+    fn backtrack(&mut self) -> bool {
+        match self.stack.pop() {
+            Some(last) => {
+                self.cursor = last + 1;
+                true
+            }
+            None => {
+                self.done = true;
+                false
             }
         }
     }
 
-    Err(anyhow!("no two numbers add to 2020"))
+    fn build(&self) -> KSum {
+        let mut chosen: Vec<(usize, i64)> = self.stack.iter().map(|i| self.sorted[*i]).collect();
+        chosen.sort_unstable();
+
+        KSum {
+            values: chosen.iter().map(|(_, v)| *v).collect(),
+            positions: chosen.iter().map(|(p, _)| *p).collect(),
+        }
+    }
 }
 
-fn calculate_part_2(input: &[i32]) -> Result<i32, anyhow::Error> {
-    for i in input {
-        for j in input {
-            for k in input {
-                let add = i + j + k;
+impl Iterator for KSumIter {
+    type Item = KSum;
+
+    fn next(&mut self) -> Option<KSum> {
+        while !self.done {
+            let partial: i128 = self.stack.iter().map(|i| self.sorted[*i].1 as i128).sum();
+            let remaining = self.k - self.stack.len();
 
-                match add {
-                    2021..=std::i32::MAX => break,
-                    2020 => return Ok(i * j * k),
-                    _ => {}
+            if remaining == 0 {
+                let found = if partial == self.target as i128 {
+                    Some(self.build())
+                } else {
+                    None
+                };
+                self.backtrack();
+                if found.is_some() {
+                    return found;
                 }
+                continue;
             }
+
+            let n = self.sorted.len();
+            if self.cursor + remaining > n {
+                self.backtrack();
+                continue;
+            }
+
+            // the values are sorted, so these bounds only get tighter as cursor grows
+            let smallest: i128 = self.sorted[self.cursor..self.cursor + remaining]
+                .iter()
+                .map(|(_, v)| *v as i128)
+                .sum();
+            let largest: i128 = self.sorted[n - remaining..]
+                .iter()
+                .map(|(_, v)| *v as i128)
+                .sum();
+            let target = self.target as i128;
+            if partial + smallest > target || partial + largest < target {
+                self.backtrack();
+                continue;
+            }
+
+            self.stack.push(self.cursor);
+            self.cursor += 1;
         }
+
+        None
     }
+}
+
+pub fn k_sums(values: &[i64], k: usize, target: i64) -> KSumIter {
+    let mut sorted: Vec<(usize, i64)> = values.iter().copied().enumerate().collect();
+    sorted.sort_by_key(|(_, v)| *v);
 
-    Err(anyhow!("no three numbers add to 2020"))
+    KSumIter {
+        sorted,
+        k,
+        target,
+        stack: vec![],
+        cursor: 0,
+        done: false,
+    }
+}
+
+pub fn count_k_sums(values: &[i64], k: usize, target: i64) -> usize {
+    k_sums(values, k, target).count()
 }
 
 #[test]
 fn test_calculate_part_1() {
-    let mut input: Vec<i32> = vec![1721, 979, 366, 299, 675, 1456];
+    let mut input: Vec<i64> = vec![1721, 979, 366, 299, 675, 1456];
     input.sort_unstable();
-    assert_eq!(514579, calculate_part_1(&input).unwrap_or(0));
+    assert_eq!(
+        514579,
+        calculate(&input, 2, 2020, Pick::First)
+            .unwrap()
+            .unwrap_or(0)
+    );
 }
 
 #[test]
 fn test_calculate_part_2() {
-    let mut input: Vec<i32> = vec![1721, 979, 366, 299, 675, 1456];
+    let mut input: Vec<i64> = vec![1721, 979, 366, 299, 675, 1456];
     input.sort_unstable();
-    assert_eq!(241861950, calculate_part_2(&input).unwrap_or(0));
+    assert_eq!(
+        241861950,
+        calculate(&input, 3, 2020, Pick::First)
+            .unwrap()
+            .unwrap_or(0)
+    );
+}
+
+#[test]
+fn test_find_k_sum_positions() {
+    let input: Vec<i64> = vec![1721, 979, 366, 299, 675, 1456];
+
+    assert_eq!(
+        Some(KSum {
+            values: vec![1721, 299],
+            positions: vec![0, 3],
+        }),
+        find_k_sum(&input, 2, 2020)
+    );
+    assert_eq!(
+        Some(KSum {
+            values: vec![979, 366, 675],
+            positions: vec![1, 2, 4],
+        }),
+        find_k_sum(&input, 3, 2020)
+    );
+}
+
+#[test]
+fn test_find_k_sum_any_k() {
+    let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7];
+
+    assert_eq!(vec![7], find_k_sum(&input, 1, 7).unwrap().values);
+    assert_eq!(
+        10,
+        find_k_sum(&input, 4, 10)
+            .unwrap()
+            .values
+            .iter()
+            .sum::<i64>()
+    );
+    assert_eq!(None, find_k_sum(&input, 4, 9));
+    assert_eq!(None, find_k_sum(&input, 8, 28));
+}
+
+#[test]
+fn test_find_k_sum_no_reuse() {
+    let input: Vec<i64> = vec![1010, 5];
+
+    assert_eq!(None, find_k_sum(&input, 2, 2020));
+    assert_eq!(
+        vec![1010, 1010],
+        find_k_sum(&[1010, 1010], 2, 2020).unwrap().values
+    );
+}
+
+#[test]
+fn test_k_sums_all() {
+    let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7];
+
+    let sums: Vec<Vec<i64>> = k_sums(&input, 2, 7).map(|s| s.values).collect();
+    assert_eq!(vec![vec![1, 6], vec![2, 5], vec![3, 4]], sums);
+    assert_eq!(5, count_k_sums(&input, 3, 12));
+    assert_eq!(0, count_k_sums(&input, 3, 100));
+    assert_eq!(1, count_k_sums(&input, 0, 0));
+}
+
+#[test]
+fn test_k_sums_duplicates() {
+    let input: Vec<i64> = vec![1010, 1010, 1010, 5];
+
+    let positions: Vec<Vec<usize>> = k_sums(&input, 2, 2020).map(|s| s.positions).collect();
+    assert_eq!(vec![vec![0, 1], vec![0, 2], vec![1, 2]], positions);
+}
+
+#[test]
+fn test_pick_product() {
+    let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7];
+
+    assert_eq!(Some(12), calculate(&input, 2, 7, Pick::Largest).unwrap());
+    assert_eq!(Some(6), calculate(&input, 2, 7, Pick::Smallest).unwrap());
+}
+
+#[test]
+fn test_no_solution_is_not_an_error() {
+    let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7];
+
+    assert_eq!(None, calculate(&input, 2, 100, Pick::First).unwrap());
+    assert_eq!(None, calculate(&input, 3, 100, Pick::Largest).unwrap());
+}
+
+#[test]
+fn test_parse_reports_line() {
+    let err = parse("1721\n979\n36x6\n").unwrap_err();
+
+    assert_eq!(
+        "line 3: can't parse '36x6': invalid digit found in string",
+        err.to_string()
+    );
+
+    let err = parse("1721\nx\n979\n\n12.5\n").unwrap_err();
+    assert_eq!(
+        "line 2: can't parse 'x': invalid digit found in string\n\
+         line 5: can't parse '12.5': invalid digit found in string",
+        err.to_string()
+    );
+}
+
+#[test]
+fn test_negative_and_wide_values() {
+    let input = parse("-5\n3000000000\n2020\n-2999997985").unwrap();
+
+    let sums: Vec<Vec<i64>> = k_sums(&input, 2, 2015).map(|s| s.values).collect();
+    assert_eq!(vec![vec![3000000000, -2999997985], vec![-5, 2020]], sums);
+    assert_eq!(
+        Some(-10100),
+        calculate(&input, 2, 2015, Pick::Largest).unwrap()
+    );
+}
+
+#[test]
+fn test_overflow_is_an_error() {
+    let input = vec![i64::MAX, i64::MIN + 2020, 3, 4];
+
+    assert!(calculate(&input, 2, 2019, Pick::First).is_err());
+    assert_eq!(Some(12), calculate(&input, 2, 7, Pick::First).unwrap());
 }
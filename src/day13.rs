@@ -1,3 +1,5 @@
+use crate::number_theory::chinese_remainder;
+
 pub fn run() -> Result<(String, String), anyhow::Error> {
     let input: String = std::fs::read_to_string("res/day13-input")?.parse()?;
 
@@ -54,38 +56,6 @@ fn parse(input: &str) -> Result<(u64, Vec<u64>), anyhow::Error> {
     Ok((start_time, busses))
 }
 
-#[allow(clippy::many_single_char_names)]
-fn egcd(a: i64, b: i64) -> (i64, i64, i64) {
-    if a == 0 {
-        (b, 0, 1)
-    } else {
-        let (g, x, y) = egcd(b % a, a);
-        (g, y - (b / a) * x, x)
-    }
-}
-
-fn mod_inv(x: i64, n: i64) -> Option<i64> {
-    let (g, x, _) = egcd(x, n);
-    if g == 1 {
-        Some((x % n + n) % n)
-    } else {
-        None
-    }
-}
-
-fn chinese_remainder(residues: &[i64], modulii: &[i64]) -> Option<i64> {
-    let prod = modulii.iter().product::<i64>();
-
-    let mut sum = 0;
-
-    for (&residue, &modulus) in residues.iter().zip(modulii) {
-        let p = prod / modulus;
-        sum += residue * mod_inv(p, modulus)? * p
-    }
-
-    Some(sum % prod)
-}
-
 #[test]
 fn test_part_1() {
     let input = "939
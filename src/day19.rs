@@ -0,0 +1,130 @@
+use crate::grammar::{parse_grammar, parse_rule, Grammar};
+use anyhow::anyhow;
+
+pub fn run() -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day19-input")?.parse()?;
+
+    let (mut grammar, messages) = parse(&input)?;
+    let result_1 = count_matching(&grammar, &messages);
+    add_loops(&mut grammar)?;
+    let result_2 = count_matching(&grammar, &messages);
+
+    Ok((format!("{}", result_1), format!("{}", result_2)))
+}
+
+fn parse(input: &str) -> Result<(Grammar, Vec<&str>), anyhow::Error> {
+    let parts: Vec<&str> = input.trim().split("\n\n").collect();
+    if parts.len() != 2 {
+        return Err(anyhow!(
+            "expected rules and messages separated by a blank line"
+        ));
+    }
+
+    let grammar = parse_grammar(parts[0])?;
+    let messages = parts[1].split('\n').map(|m| m.trim()).collect();
+
+    Ok((grammar, messages))
+}
+
+fn add_loops(grammar: &mut Grammar) -> Result<(), anyhow::Error> {
+    for line in &["8: 42 | 42 8", "11: 42 31 | 42 11 31"] {
+        let (id, rule) = parse_rule(line)?;
+        grammar.set_rule(id, rule);
+    }
+
+    Ok(())
+}
+
+fn count_matching(grammar: &Grammar, messages: &[&str]) -> usize {
+    messages
+        .iter()
+        .filter(|m| grammar.matches_fully(0, m))
+        .count()
+}
+
+#[test]
+fn test_part_1() {
+    let input = "0: 4 1 5
+1: 2 3 | 3 2
+2: 4 4 | 5 5
+3: 4 5 | 5 4
+4: \"a\"
+5: \"b\"
+
+ababbb
+bababa
+abbbab
+aaabbb
+aaaabbb";
+
+    let (grammar, messages) = parse(input).unwrap();
+    let result_1 = count_matching(&grammar, &messages);
+
+    assert_eq!(2, result_1);
+}
+
+#[cfg(test)]
+const LOOPING_EXAMPLE: &str = "42: 9 14 | 10 1
+9: 14 27 | 1 26
+10: 23 14 | 28 1
+1: \"a\"
+11: 42 31
+5: 1 14 | 15 1
+19: 14 1 | 14 14
+12: 24 14 | 19 1
+16: 15 1 | 14 14
+31: 14 17 | 1 13
+6: 14 14 | 1 14
+2: 1 24 | 14 4
+0: 8 11
+13: 14 3 | 1 12
+15: 1 | 14
+17: 14 2 | 1 7
+23: 25 1 | 22 14
+28: 16 1
+4: 1 1
+20: 14 14 | 1 15
+3: 5 14 | 16 1
+27: 1 6 | 14 18
+14: \"b\"
+21: 14 1 | 1 14
+25: 1 1 | 1 14
+22: 14 14
+8: 42
+26: 14 22 | 1 20
+18: 15 15
+7: 14 5 | 1 21
+24: 14 1
+
+abbbbbabbbaaaababbaabbbbabababbbabbbbbbabaaaa
+bbabbbbaabaabba
+babbbbaabbbbbabbbbbbaabaaabaaa
+aaabbbbbbaaaabaababaabababbabaaabbababababaaa
+bbbbbbbaaaabbbbaaabbabaaa
+bbbababbbbaaaaaaaabbababaaababaabab
+ababaaaaaabaaab
+ababaaaaabbbaba
+baabbaaaabbaaaababbaababb
+abbbbabbbbaaaababbbbbbaaaababb
+aaaaabbaabaaaaababaa
+aaaabbaaaabbaaa
+aaaabbaabbaaaaaaabbbabbbaaabbaabaaa
+babaaabbbaaabaababbaabababaaab
+aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba";
+
+#[test]
+fn test_part_2_without_loops() {
+    let (grammar, messages) = parse(LOOPING_EXAMPLE).unwrap();
+    let result_1 = count_matching(&grammar, &messages);
+
+    assert_eq!(3, result_1);
+}
+
+#[test]
+fn test_part_2() {
+    let (mut grammar, messages) = parse(LOOPING_EXAMPLE).unwrap();
+    add_loops(&mut grammar).unwrap();
+    let result_2 = count_matching(&grammar, &messages);
+
+    assert_eq!(12, result_2);
+}
@@ -1,119 +1,241 @@
+use crate::password_policy::{compile, Policy};
+use crate::password_repair::{generate, repair, Rule};
+use crate::report::ReportFormat;
+use anyhow::anyhow;
 use nom::{
-    bytes::complete::{tag, take_while, take_while_m_n},
-    character::complete::alpha1,
-    combinator::map_res,
-    sequence::tuple,
+    bytes::complete::{tag, take_while1},
+    character::complete::{anychar, digit1},
+    combinator::{all_consuming, map_res},
+    sequence::{preceded, tuple},
     IResult,
 };
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+
+pub struct Password {
+    pub line: usize,
+    pub lower: u32,
+    pub upper: u32,
+    pub enforced_char: char,
+    pub plain_text: String,
+}
 
-struct Password {
-    lower: u8,
-    upper: u8,
-    enforced_char: char,
-    plain_text: String,
+impl fmt::Display for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}: {}",
+            self.lower, self.upper, self.enforced_char, self.plain_text
+        )
+    }
 }
 
-impl Password {
-    fn validate_rule1(&self) -> bool {
-        let mut count = 0;
-        for c in self.plain_text.chars() {
-            if c == self.enforced_char {
-                count += 1;
-            }
+#[derive(Debug, PartialEq, Serialize)]
+struct ReportRow {
+    line: usize,
+    entry: String,
+    policy: String,
+    passed: bool,
+    reason: String,
+}
+
+enum ReportWriter<W: Write> {
+    Text(W),
+    Json { out: W, rows: usize },
+    Csv(Box<csv::Writer<W>>),
+}
+
+impl<W: Write> ReportWriter<W> {
+    fn new(format: ReportFormat, out: W) -> ReportWriter<W> {
+        match format {
+            ReportFormat::Text => ReportWriter::Text(out),
+            ReportFormat::Json => ReportWriter::Json { out, rows: 0 },
+            ReportFormat::Csv => ReportWriter::Csv(Box::new(csv::Writer::from_writer(out))),
         }
+    }
 
-        count >= self.lower && count <= self.upper
+    fn write(&mut self, row: &ReportRow) -> Result<(), anyhow::Error> {
+        match self {
+            ReportWriter::Text(out) => writeln!(
+                out,
+                "line {}: {} [{}] {} ({})",
+                row.line,
+                row.entry,
+                row.policy,
+                if row.passed { "pass" } else { "FAIL" },
+                row.reason
+            )?,
+            ReportWriter::Json { out, rows } => {
+                out.write_all(if *rows == 0 { b"[\n  " } else { b",\n  " })?;
+                serde_json::to_writer(&mut *out, row)?;
+                *rows += 1;
+            }
+            ReportWriter::Csv(writer) => writer.serialize(row)?,
+        }
+        Ok(())
     }
 
-    fn validate_rule2(&self) -> bool {
-        let chars: Vec<char> = self.plain_text.chars().collect();
-
-        let lower = if ((self.lower - 1) as usize) < chars.len() {
-            chars[(self.lower - 1) as usize]
-        } else {
-            '!'
-        };
-        let upper = if ((self.upper - 1) as usize) < chars.len() {
-            chars[(self.upper - 1) as usize]
-        } else {
-            '!'
-        };
-
-        (lower == self.enforced_char || upper == self.enforced_char) && lower != upper
+    fn finish(self) -> Result<(), anyhow::Error> {
+        match self {
+            ReportWriter::Text(mut out) => out.flush()?,
+            ReportWriter::Json { mut out, rows } => {
+                out.write_all(if rows == 0 { b"[]\n" } else { b"\n]\n" })?;
+                out.flush()?;
+            }
+            ReportWriter::Csv(mut writer) => writer.flush()?,
+        }
+        Ok(())
     }
 }
 
-fn from_digit(input: &str) -> Result<u8, std::num::ParseIntError> {
-    u8::from_str_radix(input, 10)
+fn from_digit(input: &str) -> Result<u32, std::num::ParseIntError> {
+    input.parse()
 }
 
-fn str_to_char(input: &str) -> Result<char, std::num::ParseIntError> {
-    let chars: Vec<char> = input.chars().collect();
-
-    Ok(chars[0])
+fn bound(input: &str) -> IResult<&str, u32> {
+    map_res(digit1, from_digit)(input)
 }
 
-fn is_digit(c: char) -> bool {
-    c.is_digit(10)
+fn entry(input: &str) -> IResult<&str, (u32, u32, char, &str)> {
+    all_consuming(tuple((
+        bound,
+        preceded(tag("-"), bound),
+        preceded(tag(" "), anychar),
+        preceded(tag(": "), take_while1(|c: char| !c.is_whitespace())),
+    )))(input)
 }
 
-fn digit(input: &str) -> IResult<&str, u8> {
-    map_res(take_while(is_digit), from_digit)(input)
+fn parse_line(line: &str, number: usize) -> Result<Password, anyhow::Error> {
+    let (_, (lower, upper, enforced_char, plain_text)) = entry(line.trim_end())
+        .map_err(|_| anyhow!("line {}: malformed entry '{}'", number, line))?;
+
+    Ok(Password {
+        line: number,
+        lower,
+        upper,
+        enforced_char,
+        plain_text: plain_text.to_owned(),
+    })
 }
 
-fn take1(input: &str) -> IResult<&str, char> {
-    map_res(take_while_m_n(1, 1, |_| true), str_to_char)(input)
+// parses one line at a time so the whole database never has to be in memory
+fn passwords<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Password, anyhow::Error>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(parse_line(&line, i + 1)),
+            Err(e) => Some(Err(anyhow!("line {}: {}", i + 1, e))),
+        })
 }
 
-fn password(input: &str) -> IResult<&str, Vec<Password>> {
-    let mut passwords = vec![];
-
-    for input in input.split('\n') {
-        if input.is_empty() {
-            break;
+// the first policy answers part 1 and the second part 2, defaulting to the puzzle's rules
+pub fn run(
+    policies: &[String],
+    report_format: Option<ReportFormat>,
+    rule: Rule,
+    fix: bool,
+    generated: Option<usize>,
+) -> Result<(String, String), anyhow::Error> {
+    if let Some(n) = generated {
+        let mut rng = rand::thread_rng();
+        for _ in 0..n {
+            println!("{}", generate(&mut rng, rule, 12)?);
         }
-        let (input, (lower, _, upper)) = tuple((digit, tag("-"), digit))(input)?;
-        let (input, _) = tag(" ")(input)?;
-        let (_input, (enforced_char, _, plain_text)) = tuple((take1, tag(": "), alpha1))(input)?;
-        passwords.push(Password {
-            lower,
-            upper,
-            enforced_char,
-            plain_text: plain_text.to_string(),
-        });
     }
-    Ok((input, passwords))
-}
 
-pub fn run() -> Result<(String, String), anyhow::Error> {
-    let input: String = std::fs::read_to_string("res/day2-input")?.parse()?;
-
-    let (_, input_vec) = password(&input).unwrap();
+    if fix {
+        let reader = BufReader::new(File::open("res/day2-input")?);
+        print_repairs(reader, rule, std::io::stdout().lock())?;
+    }
 
-    let result_1 = calculate_part_1(&input_vec)?;
-    let result_2 = calculate_part_2(&input_vec)?;
+    let reader = BufReader::new(File::open("res/day2-input")?);
+
+    let source_1 = policies.first().map(|p| p.as_str()).unwrap_or("sled");
+    let source_2 = policies.get(1).map(|p| p.as_str()).unwrap_or("toboggan");
+    let compiled = vec![
+        (source_1.to_owned(), compile(source_1)?),
+        (source_2.to_owned(), compile(source_2)?),
+    ];
+
+    let counts = match report_format {
+        Some(format) => {
+            let stdout = std::io::stdout();
+            let mut writer = ReportWriter::new(format, stdout.lock());
+            let counts = audit(reader, &compiled, Some(&mut |row| writer.write(&row)))?;
+            writer.finish()?;
+            counts
+        }
+        None => audit(reader, &compiled, None)?,
+    };
 
-    Ok((format!("{}", result_1), format!("{}", result_2)))
+    Ok((format!("{}", counts[0]), format!("{}", counts[1])))
 }
 
-fn calculate_part_1(passwords: &[Password]) -> Result<i32, anyhow::Error> {
-    let mut count = 0;
-    for p in passwords {
-        if p.validate_rule1() {
-            count += 1;
+// counts the entries valid under each policy, handing a row per entry and
+// policy to `report` when one is given
+fn audit<R: BufRead>(
+    reader: R,
+    policies: &[(String, Policy)],
+    mut report: Option<&mut dyn FnMut(ReportRow) -> Result<(), anyhow::Error>>,
+) -> Result<Vec<usize>, anyhow::Error> {
+    let mut counts = vec![0; policies.len()];
+    for password in passwords(reader) {
+        let password = password?;
+        for (i, (source, policy)) in policies.iter().enumerate() {
+            let passed = match &mut report {
+                Some(report) => {
+                    let (passed, reason) = policy.explain(&password);
+                    report(ReportRow {
+                        line: password.line,
+                        entry: password.to_string(),
+                        policy: source.clone(),
+                        passed,
+                        reason,
+                    })?;
+                    passed
+                }
+                None => policy.check(&password),
+            };
+            if passed {
+                counts[i] += 1;
+            }
         }
     }
-    Ok(count)
+
+    Ok(counts)
 }
 
-fn calculate_part_2(passwords: &[Password]) -> Result<i32, anyhow::Error> {
-    let mut count = 0;
-    for p in passwords {
-        if p.validate_rule2() {
-            count += 1;
+// lists the smallest edit for every entry that breaks `rule`
+fn print_repairs<R: BufRead, W: Write>(
+    reader: R,
+    rule: Rule,
+    mut out: W,
+) -> Result<(), anyhow::Error> {
+    for password in passwords(reader) {
+        let password = password?;
+        // one unfixable entry shouldn't stop the rest of the database
+        match repair(&password, rule) {
+            Ok(repaired) if repaired.edits.is_empty() => {}
+            Ok(repaired) => writeln!(
+                out,
+                "line {}: {} -> {} {:?}",
+                password.line, password, repaired.text, repaired.edits
+            )?,
+            Err(e) => writeln!(out, "line {}: unfixable, {}", password.line, e)?,
         }
     }
-    Ok(count)
+
+    Ok(())
+}
+
+#[cfg(test)]
+fn count_valid(input: &str, policy: &str) -> usize {
+    let policies = vec![(policy.to_owned(), compile(policy).unwrap())];
+    audit(input.as_bytes(), &policies, None).unwrap()[0]
 }
 
 #[test]
@@ -122,9 +244,7 @@ fn part1() {
 1-3 b: cdefg
 2-9 c: ccccccccc";
 
-    let (_, input_vec) = password(&input).unwrap();
-
-    let result_1 = calculate_part_1(&input_vec).unwrap();
+    let result_1 = count_valid(input, "sled");
 
     assert_eq!(2, result_1);
 }
@@ -135,9 +255,7 @@ fn part2() {
 1-3 b: cdefg
 2-9 c: ccccccccc";
 
-    let (_, input_vec) = password(&input).unwrap();
-
-    let result_1 = calculate_part_2(&input_vec).unwrap();
+    let result_1 = count_valid(input, "toboggan");
 
     assert_eq!(1, result_1);
 }
@@ -146,9 +264,7 @@ fn part2() {
 fn part2_1() {
     let input = "1-3 a: abcde";
 
-    let (_, input_vec) = password(&input).unwrap();
-
-    let result_1 = calculate_part_2(&input_vec).unwrap();
+    let result_1 = count_valid(input, "toboggan");
 
     assert_eq!(1, result_1);
 }
@@ -157,9 +273,7 @@ fn part2_1() {
 fn part2_2() {
     let input = "1-3 b: cdefg";
 
-    let (_, input_vec) = password(&input).unwrap();
-
-    let result_1 = calculate_part_2(&input_vec).unwrap();
+    let result_1 = count_valid(input, "toboggan");
 
     assert_eq!(0, result_1);
 }
@@ -168,9 +282,124 @@ fn part2_2() {
 fn part2_3() {
     let input = "2-9 c: ccccccccc";
 
-    let (_, input_vec) = password(&input).unwrap();
-
-    let result_1 = calculate_part_2(&input_vec).unwrap();
+    let result_1 = count_valid(input, "toboggan");
 
     assert_eq!(0, result_1);
 }
+
+#[test]
+fn test_report() {
+    let input = "1-3 a: abcde
+1-3 b: cdefg";
+
+    let policies = vec![("sled".to_owned(), compile("sled").unwrap())];
+    let mut rows = vec![];
+    let counts = audit(
+        input.as_bytes(),
+        &policies,
+        Some(&mut |row| {
+            rows.push(row);
+            Ok(())
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(vec![1], counts);
+    assert_eq!(
+        ReportRow {
+            line: 2,
+            entry: "1-3 b: cdefg".to_owned(),
+            policy: "sled".to_owned(),
+            passed: false,
+            reason: "found 0 'b', min 1".to_owned(),
+        },
+        rows[1]
+    );
+
+    let mut csv = vec![];
+    let mut writer = ReportWriter::new(ReportFormat::Csv, &mut csv);
+    for row in &rows {
+        writer.write(row).unwrap();
+    }
+    writer.finish().unwrap();
+    assert_eq!(
+        "line,entry,policy,passed,reason
+1,1-3 a: abcde,sled,true,\"found 1 'a', within 1..3\"
+2,1-3 b: cdefg,sled,false,\"found 0 'b', min 1\"
+",
+        String::from_utf8(csv).unwrap()
+    );
+
+    let mut json = vec![];
+    let mut writer = ReportWriter::new(ReportFormat::Json, &mut json);
+    for row in &rows {
+        writer.write(row).unwrap();
+    }
+    writer.finish().unwrap();
+    let parsed: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(2, parsed[1]["line"]);
+    assert_eq!(false, parsed[1]["passed"]);
+}
+
+#[test]
+fn test_unicode_and_wide_entries() {
+    let input = "1-2 é: éa€!9
+
+300-400 x: 1x2y_3z";
+
+    let parsed: Vec<Password> = passwords(input.as_bytes()).map(|p| p.unwrap()).collect();
+
+    assert_eq!(2, parsed.len());
+    assert_eq!('é', parsed[0].enforced_char);
+    assert_eq!("éa€!9", parsed[0].plain_text);
+    assert_eq!(3, parsed[1].line);
+    assert_eq!(400, parsed[1].upper);
+    assert_eq!(1, count_valid(input, "at(2) == 'a' and at(3) == '€'"));
+}
+
+#[test]
+fn test_malformed_entry() {
+    let input = "1-3 a: abcde
+1-3 b:
+1-3 c: ccc";
+
+    let errors: Vec<String> = passwords(input.as_bytes())
+        .filter_map(|p| p.err())
+        .map(|e| e.to_string())
+        .collect();
+
+    assert_eq!(vec!["line 2: malformed entry '1-3 b:'"], errors);
+}
+
+#[test]
+fn test_print_repairs() {
+    let input = "1-3 a: abcde
+1-3 b: cdefg
+2-9 c: ccccccccc";
+
+    let mut out = vec![];
+    print_repairs(input.as_bytes(), Rule::Toboggan, &mut out).unwrap();
+
+    assert_eq!(
+        "line 2: 1-3 b: cdefg -> bdefg [Substitute(0, 'b')]
+line 3: 2-9 c: ccccccccc -> cccccccca [Substitute(8, 'a')]
+",
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn test_print_repairs_keeps_going() {
+    let input = "3-3 a: abc
+1-3 b: cdefg";
+
+    let mut out = vec![];
+    print_repairs(input.as_bytes(), Rule::Both, &mut out).unwrap();
+
+    assert_eq!(
+        "line 1: unfixable, 3-3 a: abc can't be satisfied
+line 2: 1-3 b: cdefg -> bdefg [Substitute(0, 'b')]
+",
+        String::from_utf8(out).unwrap()
+    );
+}
@@ -0,0 +1,392 @@
+use crate::grid::Grid;
+use anyhow::anyhow;
+use std::collections::HashSet;
+
+const SEA_MONSTER: [&str; 3] = [
+    "                  # ",
+    "#    ##    ##    ###",
+    " #  #  #  #  #  #   ",
+];
+
+struct Tile {
+    id: u64,
+    grid: Grid<bool>,
+}
+
+pub fn run() -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day20-input")?.parse()?;
+
+    let tiles = parse(&input)?;
+    let result_1: u64 = find_corners(&tiles).iter().map(|t| t.id).product();
+    let image = assemble(&tiles)?;
+    let result_2 = water_roughness(&image)?;
+
+    Ok((format!("{}", result_1), format!("{}", result_2)))
+}
+
+fn parse(input: &str) -> Result<Vec<Tile>, anyhow::Error> {
+    let mut tiles = vec![];
+    for block in input.trim().split("\n\n") {
+        let mut lines = block.trim().split('\n');
+        let header = lines.next().ok_or_else(|| anyhow!("empty tile"))?;
+        let id = header
+            .strip_prefix("Tile ")
+            .and_then(|h| h.strip_suffix(':'))
+            .ok_or_else(|| anyhow!("malformed tile header: {}", header))?
+            .parse::<u64>()?;
+
+        let rows = lines
+            .map(|l| {
+                l.chars()
+                    .map(|c| match c {
+                        '#' => Ok(true),
+                        '.' => Ok(false),
+                        _ => Err(anyhow!("not a tile char")),
+                    })
+                    .collect::<Result<Vec<bool>, anyhow::Error>>()
+            })
+            .collect::<Result<Vec<Vec<bool>>, anyhow::Error>>()?;
+
+        // the border is stripped on assembly, so a tile needs an inside
+        let size = rows.len();
+        if size < 3 || rows.iter().any(|r| r.len() != size) {
+            return Err(anyhow!("tile {} is not a square of at least 3x3", id));
+        }
+        if let Some(first) = tiles.first().map(|t: &Tile| t.grid.height()) {
+            if first != size {
+                return Err(anyhow!(
+                    "tile {} is {}x{}, the others are {}x{}",
+                    id,
+                    size,
+                    size,
+                    first,
+                    first
+                ));
+            }
+        }
+
+        tiles.push(Tile {
+            id,
+            grid: Grid::from_rows(rows)?,
+        });
+    }
+
+    Ok(tiles)
+}
+
+fn bottom(grid: &Grid<bool>) -> Option<Vec<bool>> {
+    grid.row(grid.height().checked_sub(1)?)
+}
+
+fn right(grid: &Grid<bool>) -> Option<Vec<bool>> {
+    grid.column(grid.width().checked_sub(1)?)
+}
+
+// clockwise from the top
+fn edges(grid: &Grid<bool>) -> Vec<Vec<bool>> {
+    vec![grid.row(0), right(grid), bottom(grid), grid.column(0)]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn edge_matches(tile: &Tile, tiles: &[Tile], edge: &[bool]) -> bool {
+    let reversed: Vec<bool> = edge.iter().rev().copied().collect();
+    tiles
+        .iter()
+        .filter(|t| t.id != tile.id)
+        .flat_map(|t| edges(&t.grid))
+        .any(|e| e == edge || e == reversed)
+}
+
+fn unmatched_edges(tile: &Tile, tiles: &[Tile]) -> usize {
+    edges(&tile.grid)
+        .iter()
+        .filter(|e| !edge_matches(tile, tiles, e))
+        .count()
+}
+
+fn find_corners(tiles: &[Tile]) -> Vec<&Tile> {
+    tiles
+        .iter()
+        .filter(|t| unmatched_edges(t, tiles) == 2)
+        .collect()
+}
+
+fn assemble(tiles: &[Tile]) -> Result<Grid<bool>, anyhow::Error> {
+    let size = (tiles.len() as f64).sqrt() as usize;
+    if size * size != tiles.len() {
+        return Err(anyhow!("{} tiles can't form a square", tiles.len()));
+    }
+
+    let corner = find_corners(tiles)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no corner tile found"))?;
+    let start = corner
+        .grid
+        .orientations()
+        .into_iter()
+        .find(|g| {
+            [g.row(0), g.column(0)]
+                .iter()
+                .all(|e| matches!(e, Some(e) if !edge_matches(corner, tiles, e)))
+        })
+        .ok_or_else(|| anyhow!("corner tile can't be oriented"))?;
+
+    let mut used: HashSet<u64> = HashSet::new();
+    used.insert(corner.id);
+    let mut placed: Vec<Vec<Grid<bool>>> = vec![vec![start]];
+
+    for y in 0..size {
+        if y > 0 {
+            let above = bottom(&placed[y - 1][0]);
+            let (id, grid) = find_fitting(tiles, &used, |g| above.is_some() && g.row(0) == above)?;
+            used.insert(id);
+            placed.push(vec![grid]);
+        }
+        for x in 1..size {
+            let left = right(&placed[y][x - 1]);
+            let (id, grid) = find_fitting(tiles, &used, |g| left.is_some() && g.column(0) == left)?;
+            used.insert(id);
+            placed[y].push(grid);
+        }
+    }
+
+    let inner = placed[0][0].height().saturating_sub(2);
+    let mut rows = vec![];
+    for tile_row in &placed {
+        let stripped = tile_row
+            .iter()
+            .map(|g| {
+                g.sub_grid(1, 1, inner, inner)
+                    .ok_or_else(|| anyhow!("tile has no inside to keep"))
+            })
+            .collect::<Result<Vec<Grid<bool>>, anyhow::Error>>()?;
+        for y in 0..inner {
+            rows.push(stripped.iter().flat_map(|g| g.row(y)).flatten().collect());
+        }
+    }
+
+    Grid::from_rows(rows)
+}
+
+fn find_fitting<F>(
+    tiles: &[Tile],
+    used: &HashSet<u64>,
+    fits: F,
+) -> Result<(u64, Grid<bool>), anyhow::Error>
+where
+    F: Fn(&Grid<bool>) -> bool,
+{
+    for tile in tiles.iter().filter(|t| !used.contains(&t.id)) {
+        if let Some(grid) = tile.grid.orientations().into_iter().find(|g| fits(g)) {
+            return Ok((tile.id, grid));
+        }
+    }
+
+    Err(anyhow!("no tile fits"))
+}
+
+fn sea_monster() -> Vec<(usize, usize)> {
+    let mut ret = vec![];
+    for (y, line) in SEA_MONSTER.iter().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            if c == '#' {
+                ret.push((x, y));
+            }
+        }
+    }
+    ret
+}
+
+fn water_roughness(image: &Grid<bool>) -> Result<usize, anyhow::Error> {
+    let monster = sea_monster();
+    let monster_width = monster.iter().map(|(x, _)| x + 1).max().unwrap_or(0);
+    let monster_height = monster.iter().map(|(_, y)| y + 1).max().unwrap_or(0);
+
+    for oriented in image.orientations() {
+        let mut covered: HashSet<(usize, usize)> = HashSet::new();
+        for y in 0..=oriented.height().saturating_sub(monster_height) {
+            for x in 0..=oriented.width().saturating_sub(monster_width) {
+                if monster
+                    .iter()
+                    .all(|(dx, dy)| oriented.get(x + dx, y + dy) == Some(&true))
+                {
+                    covered.extend(monster.iter().map(|(dx, dy)| (x + dx, y + dy)));
+                }
+            }
+        }
+
+        if !covered.is_empty() {
+            return Ok(oriented.cells().filter(|c| **c).count() - covered.len());
+        }
+    }
+
+    Err(anyhow!("no sea monsters found"))
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Tile 2311:
+..##.#..#.
+##..#.....
+#...##..#.
+####.#...#
+##.##.###.
+##...#.###
+.#.#.#..##
+..#....#..
+###...#.#.
+..###..###
+
+Tile 1951:
+#.##...##.
+#.####...#
+.....#..##
+#...######
+.##.#....#
+.###.#####
+###.##.##.
+.###....#.
+..#.#..#.#
+#...##.#..
+
+Tile 1171:
+####...##.
+#..##.#..#
+##.#..#.#.
+.###.####.
+..###.####
+.##....##.
+.#...####.
+#.##.####.
+####..#...
+.....##...
+
+Tile 1427:
+###.##.#..
+.#..#.##..
+.#.##.#..#
+#.#.#.##.#
+....#...##
+...##..##.
+...#.#####
+.#.####.#.
+..#..###.#
+..##.#..#.
+
+Tile 1489:
+##.#.#....
+..##...#..
+.##..##...
+..#...#...
+#####...#.
+#..#.#.#.#
+...#.#.#..
+##.#...##.
+..##.##.##
+###.##.#..
+
+Tile 2473:
+#....####.
+#..#.##...
+#.##..#...
+######.#.#
+.#...#.#.#
+.#########
+.###.#..#.
+########.#
+##...##.#.
+..###.#.#.
+
+Tile 2971:
+..#.#....#
+#...###...
+#.#.###...
+##.##..#..
+.#####..##
+.#..####.#
+#..#.#..#.
+..####.###
+..#.#.###.
+...#.#.#.#
+
+Tile 2729:
+...#.#.#.#
+####.#....
+..#.#.....
+....#..#.#
+.##..##.#.
+.#.####...
+####.#.#..
+##.####...
+##..#.##..
+#.##...##.
+
+Tile 3079:
+#.#.#####.
+.#..######
+..#.......
+######....
+####.#..#.
+.#...#.##.
+#.#####.##
+..#.###...
+..#.......
+..#.###...";
+
+#[test]
+fn test_part_1() {
+    let tiles = parse(EXAMPLE).unwrap();
+    let result_1: u64 = find_corners(&tiles).iter().map(|t| t.id).product();
+
+    assert_eq!(20899048083289, result_1);
+}
+
+#[test]
+fn test_part_2() {
+    let tiles = parse(EXAMPLE).unwrap();
+    let image = assemble(&tiles).unwrap();
+    let result_2 = water_roughness(&image).unwrap();
+
+    assert_eq!(24, image.width());
+    assert_eq!(273, result_2);
+}
+
+#[test]
+fn test_find_fitting() {
+    let rows = vec![
+        vec![true, true, false],
+        vec![false, false, false],
+        vec![true, false, false],
+    ];
+    let tile = Tile {
+        id: 7,
+        grid: Grid::from_rows(rows).unwrap(),
+    };
+    let used = HashSet::new();
+
+    // only a quarter turn clockwise puts the bottom left corner on top with these edges
+    let (id, grid) = find_fitting(&[tile], &used, |g| {
+        g.row(0) == Some(vec![true, false, true]) && g.column(0) == Some(vec![true, false, false])
+    })
+    .unwrap();
+    assert_eq!(7, id);
+    assert_eq!(Some(vec![true, true, false]), grid.column(2));
+
+    let used: HashSet<u64> = [7].iter().copied().collect();
+    let tile = Tile {
+        id: 7,
+        grid: Grid::from_rows(vec![vec![true; 3]; 3]).unwrap(),
+    };
+    assert!(find_fitting(&[tile], &used, |_| true).is_err());
+}
+
+#[test]
+fn test_bad_tiles() {
+    assert!(parse("Tile 1:").is_err());
+    assert!(parse("Tile 1:\n#.#").is_err());
+    assert!(parse("Tile 1:\n#.#\n...\n").is_err());
+    assert!(parse("Tile 1:\n#.#\n...\n.#.\n\nTile 2:\n#...\n....\n....\n....").is_err());
+    assert_eq!(1, parse("Tile 1:\n#.#\n...\n.#.").unwrap().len());
+}
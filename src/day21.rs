@@ -0,0 +1,108 @@
+use crate::constraints::resolve_unique;
+use anyhow::anyhow;
+use std::collections::{HashMap, HashSet};
+
+struct Food {
+    ingredients: HashSet<String>,
+    allergens: HashSet<String>,
+}
+
+pub fn run() -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day21-input")?.parse()?;
+
+    let foods = parse(&input)?;
+    let result_1 = count_safe(&foods);
+    let result_2 = dangerous_list(&foods)?;
+
+    Ok((format!("{}", result_1), result_2))
+}
+
+fn parse(input: &str) -> Result<Vec<Food>, anyhow::Error> {
+    let mut foods = vec![];
+    for line in input.trim().split('\n') {
+        let (ingredients, allergens) = match line.trim().strip_suffix(')') {
+            Some(l) => {
+                let parts: Vec<&str> = l.split(" (contains ").collect();
+                if parts.len() != 2 {
+                    return Err(anyhow!("malformed food line: {}", line));
+                }
+                (parts[0], parts[1])
+            }
+            None => (line.trim(), ""),
+        };
+
+        foods.push(Food {
+            ingredients: ingredients.split(' ').map(|s| s.to_owned()).collect(),
+            allergens: allergens
+                .split(", ")
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned())
+                .collect(),
+        });
+    }
+
+    Ok(foods)
+}
+
+fn allergen_candidates(foods: &[Food]) -> HashMap<String, HashSet<String>> {
+    let mut candidates: HashMap<String, HashSet<String>> = HashMap::new();
+    for food in foods {
+        for allergen in &food.allergens {
+            let entry = candidates
+                .entry(allergen.clone())
+                .or_insert_with(|| food.ingredients.clone());
+            *entry = entry.intersection(&food.ingredients).cloned().collect();
+        }
+    }
+    candidates
+}
+
+fn count_safe(foods: &[Food]) -> usize {
+    let unsafe_ingredients: HashSet<String> =
+        allergen_candidates(foods).into_values().flatten().collect();
+
+    foods
+        .iter()
+        .flat_map(|f| f.ingredients.iter())
+        .filter(|i| !unsafe_ingredients.contains(*i))
+        .count()
+}
+
+fn dangerous_list(foods: &[Food]) -> Result<String, anyhow::Error> {
+    let mut mapping: Vec<(String, String)> = resolve_unique(allergen_candidates(foods))?
+        .into_iter()
+        .collect();
+    mapping.sort();
+
+    Ok(mapping
+        .into_iter()
+        .map(|(_, ingredient)| ingredient)
+        .collect::<Vec<String>>()
+        .join(","))
+}
+
+#[test]
+fn test_part_1() {
+    let input = "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)
+trh fvjkl sbzzf mxmxvkd (contains dairy)
+sqjhc fvjkl (contains soy)
+sqjhc mxmxvkd sbzzf (contains fish)";
+
+    let foods = parse(input).unwrap();
+    let result_1 = count_safe(&foods);
+
+    assert_eq!(5, result_1);
+}
+
+#[test]
+fn test_part_2() {
+    let input = "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)
+trh fvjkl sbzzf mxmxvkd (contains dairy)
+sqjhc fvjkl (contains soy)
+sqjhc mxmxvkd sbzzf (contains fish)";
+
+    let foods = parse(input).unwrap();
+    let result_2 = dangerous_list(&foods).unwrap();
+
+    assert_eq!("mxmxvkd,sqjhc,fvjkl", result_2);
+}
@@ -0,0 +1,273 @@
+use anyhow::anyhow;
+use std::collections::{HashSet, VecDeque};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Player {
+    One,
+    Two,
+}
+
+struct Combat {
+    recursive: bool,
+    games: usize,
+    log: Option<Vec<String>>,
+}
+
+impl Combat {
+    fn new(recursive: bool) -> Combat {
+        Combat {
+            recursive,
+            games: 0,
+            log: None,
+        }
+    }
+
+    fn with_log(mut self) -> Combat {
+        self.log = Some(vec![]);
+        self
+    }
+
+    fn replay_log(&self) -> &[String] {
+        self.log.as_deref().unwrap_or(&[])
+    }
+
+    // only builds the line when there is a log to keep it
+    fn record<F: FnOnce() -> String>(&mut self, line: F) {
+        if let Some(log) = &mut self.log {
+            log.push(line());
+        }
+    }
+
+    fn play(&mut self, mut one: VecDeque<u32>, mut two: VecDeque<u32>) -> (Player, VecDeque<u32>) {
+        self.games += 1;
+        let game = self.games;
+        self.record(|| format!("=== Game {} ===", game));
+
+        let mut seen: HashSet<Vec<u32>> = HashSet::new();
+        let mut round = 0;
+        while !one.is_empty() && !two.is_empty() {
+            round += 1;
+            if self.recursive && !seen.insert(state(&one, &two)) {
+                self.record(|| format!("Player 1 wins game {} by repetition!", game));
+                return (Player::One, one);
+            }
+
+            self.record(|| format!("-- Round {} (Game {}) --", round, game));
+            self.record(|| format!("Player 1's deck: {}", deck_string(&one)));
+            self.record(|| format!("Player 2's deck: {}", deck_string(&two)));
+
+            let card_one = one.pop_front().unwrap();
+            let card_two = two.pop_front().unwrap();
+            self.record(|| format!("Player 1 plays: {}", card_one));
+            self.record(|| format!("Player 2 plays: {}", card_two));
+
+            let winner = if self.recursive
+                && one.len() >= card_one as usize
+                && two.len() >= card_two as usize
+            {
+                self.record(|| "Playing a sub-game to determine the winner...".to_owned());
+                let sub_one = one.iter().take(card_one as usize).copied().collect();
+                let sub_two = two.iter().take(card_two as usize).copied().collect();
+                let (winner, _) = self.play(sub_one, sub_two);
+                self.record(|| format!("...anyway, back to game {}.", game));
+                winner
+            } else if card_one > card_two {
+                Player::One
+            } else {
+                Player::Two
+            };
+
+            self.record(|| {
+                format!(
+                    "Player {} wins round {} of game {}!",
+                    if winner == Player::One { 1 } else { 2 },
+                    round,
+                    game
+                )
+            });
+            match winner {
+                Player::One => {
+                    one.push_back(card_one);
+                    one.push_back(card_two);
+                }
+                Player::Two => {
+                    two.push_back(card_two);
+                    two.push_back(card_one);
+                }
+            }
+        }
+
+        if two.is_empty() {
+            self.record(|| format!("The winner of game {} is player 1!", game));
+            (Player::One, one)
+        } else {
+            self.record(|| format!("The winner of game {} is player 2!", game));
+            (Player::Two, two)
+        }
+    }
+}
+
+// both decks in one allocation, the set compares whole states on a hash match
+// so a collision can't end a game early
+fn state(one: &VecDeque<u32>, two: &VecDeque<u32>) -> Vec<u32> {
+    let mut state = Vec::with_capacity(one.len() + two.len() + 1);
+    state.push(one.len() as u32);
+    state.extend(one);
+    state.extend(two);
+    state
+}
+
+fn deck_string(deck: &VecDeque<u32>) -> String {
+    deck.iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn score(deck: &VecDeque<u32>) -> u64 {
+    deck.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, c)| (i as u64 + 1) * *c as u64)
+        .sum()
+}
+
+pub fn run(replay: bool) -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day22-input")?.parse()?;
+
+    let (one, two) = parse(&input)?;
+    let mut games = vec![Combat::new(false), Combat::new(true)];
+    if replay {
+        games = games.into_iter().map(|g| g.with_log()).collect();
+    }
+
+    let (_, deck) = games[0].play(one.clone(), two.clone());
+    let result_1 = score(&deck);
+    let (_, deck) = games[1].play(one, two);
+    let result_2 = score(&deck);
+
+    for line in games.iter().flat_map(|g| g.replay_log()) {
+        println!("{}", line);
+    }
+
+    Ok((format!("{}", result_1), format!("{}", result_2)))
+}
+
+fn parse_deck(input: &str) -> Result<VecDeque<u32>, anyhow::Error> {
+    input
+        .trim()
+        .split('\n')
+        .skip(1)
+        .map(|s| s.trim().parse::<u32>().map_err(|e| anyhow!(e)))
+        .collect()
+}
+
+fn parse(input: &str) -> Result<(VecDeque<u32>, VecDeque<u32>), anyhow::Error> {
+    let decks: Vec<&str> = input.trim().split("\n\n").collect();
+    if decks.len() != 2 {
+        return Err(anyhow!("expected exactly two decks"));
+    }
+
+    Ok((parse_deck(decks[0])?, parse_deck(decks[1])?))
+}
+
+#[test]
+fn test_part_1() {
+    let input = "Player 1:
+9
+2
+6
+3
+1
+
+Player 2:
+5
+8
+4
+7
+10";
+
+    let (one, two) = parse(input).unwrap();
+    let (winner, deck) = Combat::new(false).play(one, two);
+
+    assert_eq!(Player::Two, winner);
+    assert_eq!(306, score(&deck));
+}
+
+#[test]
+fn test_part_2() {
+    let input = "Player 1:
+9
+2
+6
+3
+1
+
+Player 2:
+5
+8
+4
+7
+10";
+
+    let (one, two) = parse(input).unwrap();
+    let (winner, deck) = Combat::new(true).play(one, two);
+
+    assert_eq!(Player::Two, winner);
+    assert_eq!(291, score(&deck));
+}
+
+#[test]
+fn test_part_2_infinite() {
+    let input = "Player 1:
+43
+19
+
+Player 2:
+2
+29
+14";
+
+    let (one, two) = parse(input).unwrap();
+    let (winner, _) = Combat::new(true).play(one, two);
+
+    assert_eq!(Player::One, winner);
+}
+
+#[test]
+fn test_replay_log() {
+    let input = "Player 1:
+9
+2
+6
+3
+1
+
+Player 2:
+5
+8
+4
+7
+10";
+
+    let (one, two) = parse(input).unwrap();
+    let mut game = Combat::new(false).with_log();
+    game.play(one, two);
+    let log = game.replay_log();
+
+    assert_eq!("=== Game 1 ===", log[0]);
+    assert_eq!("-- Round 1 (Game 1) --", log[1]);
+    assert_eq!("Player 1's deck: 9, 2, 6, 3, 1", log[2]);
+    assert_eq!("Player 1 wins round 1 of game 1!", log[6]);
+    assert_eq!("The winner of game 1 is player 2!", log[log.len() - 1]);
+}
+
+#[test]
+fn test_state_keeps_decks_apart() {
+    let deck = |cards: &[u32]| cards.iter().copied().collect::<VecDeque<u32>>();
+
+    assert_ne!(
+        state(&deck(&[1, 2]), &deck(&[3])),
+        state(&deck(&[1]), &deck(&[2, 3]))
+    );
+}
@@ -0,0 +1,184 @@
+use anyhow::anyhow;
+
+// next[label] holds the label of the cup clockwise of `label`; index 0 is unused
+pub struct CupRing {
+    next: Vec<u32>,
+    current: u32,
+}
+
+impl CupRing {
+    pub fn new(labels: &[u32], total: usize) -> Result<CupRing, anyhow::Error> {
+        let mut order = labels.to_vec();
+        if order.is_empty() {
+            return Err(anyhow!("no cups"));
+        }
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        if sorted.iter().enumerate().any(|(i, l)| *l as usize != i + 1) {
+            return Err(anyhow!("cup labels must be 1..={}", labels.len()));
+        }
+        order.extend(labels.len() as u32 + 1..=total as u32);
+        // a move picks up three cups and needs a free one left as destination
+        if order.len() < 4 {
+            return Err(anyhow!(
+                "{} cups are too few, a ring needs at least 4",
+                order.len()
+            ));
+        }
+
+        let mut next = vec![0; order.len() + 1];
+        for (i, label) in order.iter().enumerate() {
+            next[*label as usize] = order[(i + 1) % order.len()];
+        }
+
+        Ok(CupRing {
+            next,
+            current: order[0],
+        })
+    }
+
+    pub fn step(&mut self) {
+        let max = (self.next.len() - 1) as u32;
+        let first = self.next[self.current as usize];
+        let second = self.next[first as usize];
+        let third = self.next[second as usize];
+
+        let mut destination = self.current;
+        loop {
+            destination = if destination == 1 {
+                max
+            } else {
+                destination - 1
+            };
+            if destination != first && destination != second && destination != third {
+                break;
+            }
+        }
+
+        self.next[self.current as usize] = self.next[third as usize];
+        self.next[third as usize] = self.next[destination as usize];
+        self.next[destination as usize] = first;
+        self.current = self.next[self.current as usize];
+    }
+
+    pub fn play(&mut self, moves: usize) {
+        for _ in 0..moves {
+            self.step();
+        }
+    }
+
+    // the cups clockwise of `label`, or None when there is no such cup
+    pub fn after(&self, label: u32) -> Option<CupIter<'_>> {
+        if label == 0 || label as usize >= self.next.len() {
+            return None;
+        }
+
+        Some(CupIter {
+            ring: self,
+            start: label,
+            pos: self.next[label as usize],
+        })
+    }
+}
+
+pub struct CupIter<'a> {
+    ring: &'a CupRing,
+    start: u32,
+    pos: u32,
+}
+
+impl Iterator for CupIter<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.pos == self.start {
+            return None;
+        }
+        let ret = self.pos;
+        self.pos = self.ring.next[self.pos as usize];
+        Some(ret)
+    }
+}
+
+pub fn run() -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day23-input")?.parse()?;
+
+    let labels = parse(&input)?;
+    let result_1 = calc_1(&labels, 100)?;
+    let result_2 = calc_2(&labels)?;
+
+    Ok((result_1, format!("{}", result_2)))
+}
+
+fn parse(input: &str) -> Result<Vec<u32>, anyhow::Error> {
+    input
+        .trim()
+        .chars()
+        .map(|c| {
+            c.to_digit(10)
+                .ok_or_else(|| anyhow!("not a cup label: {}", c))
+        })
+        .collect()
+}
+
+fn calc_1(labels: &[u32], moves: usize) -> Result<String, anyhow::Error> {
+    let mut ring = CupRing::new(labels, labels.len())?;
+    ring.play(moves);
+
+    Ok(ring
+        .after(1)
+        .ok_or_else(|| anyhow!("no cup 1"))?
+        .map(|l| l.to_string())
+        .collect())
+}
+
+fn calc_2(labels: &[u32]) -> Result<u64, anyhow::Error> {
+    let mut ring = CupRing::new(labels, 1_000_000)?;
+    ring.play(10_000_000);
+
+    Ok(ring
+        .after(1)
+        .ok_or_else(|| anyhow!("no cup 1"))?
+        .take(2)
+        .map(|l| l as u64)
+        .product())
+}
+
+#[test]
+fn test_part_1() {
+    let labels = parse("389125467").unwrap();
+
+    assert_eq!("92658374", calc_1(&labels, 10).unwrap());
+    assert_eq!("67384529", calc_1(&labels, 100).unwrap());
+}
+
+#[test]
+fn test_part_2() {
+    let labels = parse("389125467").unwrap();
+
+    assert_eq!(149245887792, calc_2(&labels).unwrap());
+}
+
+#[test]
+fn test_invalid_labels() {
+    assert!(CupRing::new(&[1, 3], 2).is_err());
+}
+
+#[test]
+fn test_small_rings() {
+    assert!(CupRing::new(&[2, 1, 3], 3).is_err());
+    assert!(CupRing::new(&[1], 1).is_err());
+    assert!(CupRing::new(&[2, 1, 3], 4).is_ok());
+}
+
+#[test]
+fn test_after_unknown_label() {
+    let ring = CupRing::new(&parse("389125467").unwrap(), 9).unwrap();
+
+    assert!(ring.after(0).is_none());
+    assert!(ring.after(10).is_none());
+    assert_eq!(
+        vec![2, 5, 4, 6, 7, 3, 8, 9],
+        ring.after(1).unwrap().collect::<Vec<u32>>()
+    );
+}
@@ -0,0 +1,161 @@
+use anyhow::anyhow;
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::combinator::{all_consuming, map};
+use nom::multi::many0;
+use nom::IResult;
+use std::collections::{HashMap, HashSet};
+
+// axial coordinates, q grows to the east and r to the south-east
+type Hex = (i32, i32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+const DIRECTIONS: [Direction; 6] = [
+    Direction::East,
+    Direction::SouthEast,
+    Direction::SouthWest,
+    Direction::West,
+    Direction::NorthWest,
+    Direction::NorthEast,
+];
+
+impl Direction {
+    fn offset(self) -> Hex {
+        match self {
+            Direction::East => (1, 0),
+            Direction::SouthEast => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (0, -1),
+            Direction::NorthEast => (1, -1),
+        }
+    }
+}
+
+fn neighbours((q, r): Hex) -> impl Iterator<Item = Hex> {
+    DIRECTIONS.iter().map(move |d| {
+        let (dq, dr) = d.offset();
+        (q + dq, r + dr)
+    })
+}
+
+fn direction(input: &str) -> IResult<&str, Direction> {
+    alt((
+        map(tag("se"), |_| Direction::SouthEast),
+        map(tag("sw"), |_| Direction::SouthWest),
+        map(tag("nw"), |_| Direction::NorthWest),
+        map(tag("ne"), |_| Direction::NorthEast),
+        map(tag("e"), |_| Direction::East),
+        map(tag("w"), |_| Direction::West),
+    ))(input)
+}
+
+fn path(input: &str) -> IResult<&str, Vec<Direction>> {
+    all_consuming(many0(direction))(input)
+}
+
+fn walk(directions: &[Direction]) -> Hex {
+    directions.iter().fold((0, 0), |(q, r), d| {
+        let (dq, dr) = d.offset();
+        (q + dq, r + dr)
+    })
+}
+
+pub fn run() -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day24-input")?.parse()?;
+
+    let black = parse(&input)?;
+    let result_1 = black.len();
+    let result_2 = live(black, 100).len();
+
+    Ok((format!("{}", result_1), format!("{}", result_2)))
+}
+
+fn parse(input: &str) -> Result<HashSet<Hex>, anyhow::Error> {
+    let mut black = HashSet::new();
+    for line in input.trim().split('\n') {
+        let (_, directions) = path(line.trim()).map_err(|e| anyhow!(format!("{:?}", e)))?;
+        let tile = walk(&directions);
+        if !black.remove(&tile) {
+            black.insert(tile);
+        }
+    }
+
+    Ok(black)
+}
+
+fn step(black: &HashSet<Hex>) -> HashSet<Hex> {
+    let mut counts: HashMap<Hex, usize> = HashMap::new();
+    for tile in black {
+        for n in neighbours(*tile) {
+            *counts.entry(n).or_insert(0) += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter(|(tile, count)| *count == 2 || (*count == 1 && black.contains(tile)))
+        .map(|(tile, _)| tile)
+        .collect()
+}
+
+fn live(mut black: HashSet<Hex>, days: usize) -> HashSet<Hex> {
+    for _ in 0..days {
+        black = step(&black);
+    }
+    black
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "sesenwnenenewseeswwswswwnenewsewsw
+neeenesenwnwwswnenewnwwsewnenwseswesw
+seswneswswsenwwnwse
+nwnwneseeswswnenewneswwnewseswneseene
+swweswneswnenwsewnwneneseenw
+eesenwseswswnenwswnwnwsewwnwsene
+sewnenenenesenwsewnenwwwse
+wenwwweseeeweswwwnwwe
+wsweesenenewnwwnwsenewsenwwsesesenwne
+neeswseenwwswnwswswnw
+nenwswwsewswnenenewsenwsenwnesesenew
+enewnwewneswsewnwswenweswnenwsenwsw
+sweneswneswneneenwnewenewwneswswnese
+swwesenesewenwneswnwwneseswwne
+enesenwswwswneneswsenwnewswseenwsese
+wnwnesenesenenwwnenwsewesewsesesew
+nenewswnwewswnenesenwnesewesw
+eneswnwswnwsenenwnwnwwseeswneewsenese
+neswnwewnwnwseenwseesewsenwsweewe
+wseweeenwnesenwwwswnew";
+
+#[test]
+fn test_walk() {
+    let (_, directions) = path("nwwswee").unwrap();
+
+    assert_eq!((0, 0), walk(&directions));
+}
+
+#[test]
+fn test_part_1() {
+    let black = parse(EXAMPLE).unwrap();
+
+    assert_eq!(10, black.len());
+}
+
+#[test]
+fn test_part_2() {
+    let black = parse(EXAMPLE).unwrap();
+
+    assert_eq!(15, live(black.clone(), 1).len());
+    assert_eq!(37, live(black.clone(), 10).len());
+    assert_eq!(2208, live(black, 100).len());
+}
@@ -0,0 +1,53 @@
+use crate::number_theory::{discrete_log, mod_pow};
+use anyhow::anyhow;
+
+const SUBJECT: i64 = 7;
+const MODULUS: i64 = 20201227;
+
+pub fn run() -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day25-input")?.parse()?;
+
+    let (card, door) = parse(&input)?;
+    let result_1 = encryption_key(card, door)?;
+
+    Ok((format!("{}", result_1), "no part 2 on day 25".to_owned()))
+}
+
+fn parse(input: &str) -> Result<(i64, i64), anyhow::Error> {
+    let keys: Vec<i64> = input
+        .trim()
+        .split('\n')
+        .map(|s| s.trim().parse::<i64>())
+        .collect::<Result<Vec<i64>, std::num::ParseIntError>>()?;
+    if keys.len() != 2 {
+        return Err(anyhow!("expected a card and a door public key"));
+    }
+
+    Ok((keys[0], keys[1]))
+}
+
+fn loop_size(public_key: i64) -> Result<i64, anyhow::Error> {
+    discrete_log(SUBJECT, public_key, MODULUS)
+        .ok_or_else(|| anyhow!("no loop size produces {}", public_key))
+}
+
+fn encryption_key(card: i64, door: i64) -> Result<i64, anyhow::Error> {
+    let card_loop_size = loop_size(card)?;
+
+    mod_pow(door, card_loop_size, MODULUS)
+        .ok_or_else(|| anyhow!("modulus {} must be at least 1", MODULUS))
+}
+
+#[test]
+fn test_loop_size() {
+    assert_eq!(8, loop_size(5764801).unwrap());
+    assert_eq!(11, loop_size(17807724).unwrap());
+}
+
+#[test]
+fn test_part_1() {
+    let (card, door) = parse("5764801\n17807724").unwrap();
+
+    assert_eq!(14897079, encryption_key(card, door).unwrap());
+    assert_eq!(14897079, encryption_key(door, card).unwrap());
+}
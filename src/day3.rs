@@ -1,71 +1,657 @@
 use anyhow::anyhow;
+use rand::Rng;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 
-struct Map {
-    rows: Vec<Vec<bool>>,
+// what happens to a coordinate that falls off one side of the map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    Wrap,
+    Clamp,
+    Wall,
+    Error,
 }
 
-impl Map {
-    fn is_tree(&self, x: usize, y: usize) -> Result<bool, anyhow::Error> {
-        if y > self.rows.len() {
-            return Err(anyhow!("outside of map"));
+impl FromStr for Edge {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Edge, anyhow::Error> {
+        match s {
+            "wrap" => Ok(Edge::Wrap),
+            "clamp" => Ok(Edge::Clamp),
+            "wall" => Ok(Edge::Wall),
+            "error" => Ok(Edge::Error),
+            _ => Err(anyhow!("edge must be one of wrap, clamp, wall or error")),
+        }
+    }
+}
+
+impl Edge {
+    // maps i onto 0..size, None when it hits a wall
+    fn resolve(self, i: i64, size: usize) -> Result<Option<usize>, anyhow::Error> {
+        let end = size as i64;
+        if (0..end).contains(&i) {
+            return Ok(Some(i as usize));
+        }
+
+        match self {
+            Edge::Wrap | Edge::Clamp | Edge::Wall if size == 0 => Ok(None),
+            Edge::Wrap => Ok(Some(i.rem_euclid(end) as usize)),
+            Edge::Clamp => Ok(Some(i.clamp(0, end - 1) as usize)),
+            Edge::Wall => Ok(None),
+            Edge::Error => Err(anyhow!("{} is outside of map 0..{}", i, size)),
         }
+    }
+
+    // a route ends at a wall or an error, it can go on past a wrapping or clamped edge
+    fn ends_route(self) -> bool {
+        matches!(self, Edge::Wall | Edge::Error)
+    }
+}
 
-        let r = &self.rows[y];
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Terrain {
+    Open,
+    Tree,
+    Rock,
+    Ice,
+}
 
-        Ok(r[x % r.len()])
+impl Terrain {
+    fn from_symbol(c: char) -> Option<Terrain> {
+        match c {
+            '.' => Some(Terrain::Open),
+            '#' => Some(Terrain::Tree),
+            '^' => Some(Terrain::Rock),
+            '~' => Some(Terrain::Ice),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Terrain::Open => '.',
+            Terrain::Tree => '#',
+            Terrain::Rock => '^',
+            Terrain::Ice => '~',
+        }
+    }
+}
+
+// the price of landing on each kind of square, counting trees by default
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Costs {
+    pub open: u64,
+    pub tree: u64,
+    pub rock: u64,
+    pub ice: u64,
+}
+
+impl Costs {
+    const TREES: Costs = Costs {
+        open: 0,
+        tree: 1,
+        rock: 0,
+        ice: 0,
+    };
+
+    fn of(&self, terrain: Terrain) -> u64 {
+        match terrain {
+            Terrain::Open => self.open,
+            Terrain::Tree => self.tree,
+            Terrain::Rock => self.rock,
+            Terrain::Ice => self.ice,
+        }
+    }
+}
+
+// overrides on top of the tree count, like "rock=5,ice=2"
+impl FromStr for Costs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Costs, anyhow::Error> {
+        let mut costs = Costs::TREES;
+        for pair in s.split(',').filter(|p| !p.trim().is_empty()) {
+            let (name, cost) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("cost must be written as terrain=cost"))?;
+            let cost = cost.trim().parse()?;
+            match name.trim() {
+                "open" => costs.open = cost,
+                "tree" => costs.tree = cost,
+                "rock" => costs.rock = cost,
+                "ice" => costs.ice = cost,
+                other => return Err(anyhow!("unknown terrain '{}'", other)),
+            }
+        }
+
+        Ok(costs)
+    }
+}
+
+// edge is for the sides and edge_y for the top and bottom
+#[derive(Debug)]
+pub struct Map {
+    rows: Vec<Vec<Terrain>>,
+    edge: Edge,
+    edge_y: Edge,
+}
+
+impl Map {
+    fn with_edge(self, edge: Edge) -> Map {
+        Map { edge, ..self }
+    }
+
+    fn with_edge_y(self, edge_y: Edge) -> Map {
+        Map { edge_y, ..self }
+    }
+
+    // where x ends up on the map, None past a wall
+    fn column(&self, x: i64) -> Result<Option<usize>, anyhow::Error> {
+        self.edge.resolve(x, self.width())
+    }
+
+    // where y ends up on the map, None past a wall
+    fn row(&self, y: i64) -> Result<Option<usize>, anyhow::Error> {
+        self.edge_y.resolve(y, self.len())
+    }
+
+    // a route stops at the bottom like in the puzzle unless it can go on from there
+    fn ends_at_bottom(&self) -> bool {
+        self.edge_y.ends_route()
+    }
+
+    fn terrain(&self, x: i64, y: i64) -> Result<Option<Terrain>, anyhow::Error> {
+        match (self.column(x)?, self.row(y)?) {
+            (Some(x), Some(y)) => Ok(Some(self.rows[y][x])),
+            _ => Ok(None),
+        }
     }
 
     fn len(&self) -> usize {
         self.rows.len()
     }
+
+    fn width(&self) -> usize {
+        self.rows.first().map(|r| r.len()).unwrap_or(0)
+    }
+}
+
+// dx may be negative to go left, dy has to move down at least one row
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slope {
+    pub dx: i64,
+    pub dy: usize,
 }
 
-pub fn run() -> Result<(String, String), anyhow::Error> {
+impl FromStr for Slope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Slope, anyhow::Error> {
+        let (dx, dy) = s
+            .split_once(',')
+            .ok_or_else(|| anyhow!("slope must be written as dx,dy"))?;
+        let slope = Slope {
+            dx: dx.trim().parse()?,
+            dy: dy.trim().parse()?,
+        };
+        if slope.dy == 0 {
+            return Err(anyhow!("slope {} never leaves the first row", s));
+        }
+
+        Ok(slope)
+    }
+}
+
+// everything day 3 can do besides printing the answers
+pub struct Options<'a> {
+    pub slopes: &'a [Slope],
+    pub survey: Option<i64>,
+    pub render: bool,
+    pub ppm: Option<&'a str>,
+    pub edge: Edge,
+    pub edge_y: Edge,
+    pub costs: Option<Costs>,
+    pub generate: Option<usize>,
+}
+
+// part 2 multiplies the given slopes, or the puzzle's five when there are none
+pub fn run(options: &Options) -> Result<(String, String), anyhow::Error> {
+    let slopes = options.slopes;
+
+    if let Some(rows) = options.generate {
+        print!("{}", generate_map(&mut rand::thread_rng(), rows, 31));
+    }
+
     let input: String = std::fs::read_to_string("res/day3-input")?.parse()?;
 
-    let map = parse_map(&input)?;
+    let counted = if slopes.is_empty() {
+        &PUZZLE_SLOPES[..]
+    } else {
+        slopes
+    };
 
-    let result1 = calc_1(&map)?;
+    // the packed map always wraps, stops at the bottom and only knows trees and
+    // open snow, when it can answer both parts the full map is never built
+    let answers_only = options.costs.is_none()
+        && !options.render
+        && options.ppm.is_none()
+        && options.survey.is_none();
+    if answers_only && options.edge == Edge::Wrap && options.edge_y.ends_route() {
+        if let Ok(packed) = PackedMap::parse(&input) {
+            let mut walked = vec![PART_1_SLOPE];
+            walked.extend_from_slice(counted);
+            let counts = packed.count_trees(&walked)?;
+            let result2: u64 = counts[1..].iter().product();
 
-    let mut result2: u64 = 1;
-    for (d_x, d_y) in &[(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)] {
-        result2 *= calc_2(&map, d_x, d_y).unwrap();
+            return Ok((format!("{}", counts[0]), format!("{}", result2)));
+        }
+    }
+
+    let map = parse_map(&input)?
+        .with_edge(options.edge)
+        .with_edge_y(options.edge_y);
+
+    if let Some(costs) = options.costs {
+        let weighed = if slopes.is_empty() {
+            &PUZZLE_SLOPES[..]
+        } else {
+            slopes
+        };
+        for slope in weighed {
+            println!(
+                "right {} down {}: costs {}",
+                slope.dx,
+                slope.dy,
+                route_cost(&map, *slope, &costs)?
+            );
+        }
+    }
+
+    if options.render {
+        let part_1 = [PART_1_SLOPE];
+        let shown = if slopes.is_empty() {
+            &part_1[..]
+        } else {
+            slopes
+        };
+        print!("{}", render_ascii(&map, shown)?);
+    }
+
+    if let Some(path) = options.ppm {
+        let shown = if slopes.is_empty() {
+            PUZZLE_SLOPES.to_vec()
+        } else {
+            slopes.to_vec()
+        };
+        let mut out = BufWriter::new(File::create(path)?);
+        write_ppm(&map, &shown, 2, &mut out)?;
+        out.flush()?;
     }
 
+    if let Some(size) = options.survey {
+        let counts = survey(&map, -size..=size, 1..=size.max(1) as usize)?;
+        for (slope, trees) in &counts {
+            println!("right {} down {}: {} trees", slope.dx, slope.dy, trees);
+        }
+        if let Some((slope, trees)) = fewest_trees(&counts) {
+            println!(
+                "fewest trees: right {} down {} with {}",
+                slope.dx, slope.dy, trees
+            );
+        }
+    }
+
+    let result1 = calc_1(&map)?;
+    let result2 = counted
+        .iter()
+        .map(|s| count_trees(&map, *s))
+        .product::<Result<u64, anyhow::Error>>()?;
+
     Ok((format!("{}", result1), format!("{}", result2)))
 }
 
-fn parse_map(input: &str) -> Result<Map, anyhow::Error> {
-    let mut rows: Vec<Vec<bool>> = vec![];
+pub fn parse_map(input: &str) -> Result<Map, anyhow::Error> {
+    let mut rows: Vec<Vec<Terrain>> = vec![];
 
-    for r in input.split('\n') {
+    for (i, r) in input.split('\n').enumerate() {
         let mut row = vec![];
-        for c in r.chars() {
-            row.push(c == '#')
+        for (j, c) in r.trim_end().chars().enumerate() {
+            row.push(Terrain::from_symbol(c).ok_or_else(|| {
+                anyhow!("line {}, column {}: unknown terrain '{}'", i + 1, j + 1, c)
+            })?);
+        }
+        if let Some(first) = rows.first() {
+            if !row.is_empty() && row.len() != first.len() {
+                return Err(anyhow!(
+                    "line {}: expected {} squares, found {}",
+                    i + 1,
+                    first.len(),
+                    row.len()
+                ));
+            }
         }
         if !row.is_empty() {
             rows.push(row);
         }
     }
 
-    Ok(Map { rows })
+    Ok(Map {
+        rows,
+        edge: Edge::Wrap,
+        edge_y: Edge::Error,
+    })
 }
 
 fn calc_1(map: &Map) -> Result<u64, anyhow::Error> {
-    let mut nr_of_trees = 0;
-    for r in 0..map.len() {
-        if map.is_tree(r * 3, r)? {
-            nr_of_trees += 1;
+    count_trees(map, PART_1_SLOPE)
+}
+
+// walks from the top left corner summing the cost of every square landed on
+fn route_cost(map: &Map, slope: Slope, costs: &Costs) -> Result<u64, anyhow::Error> {
+    Ok(route(map, slope)?.iter().map(|l| costs.of(l.terrain)).sum())
+}
+
+pub fn count_trees(map: &Map, slope: Slope) -> Result<u64, anyhow::Error> {
+    route_cost(map, slope, &Costs::TREES)
+}
+
+// one bit per square, set for trees, with every row in a single allocation.
+// Always wraps horizontally like the puzzle does.
+pub struct PackedMap {
+    width: usize,
+    stride: usize,
+    bits: Vec<u64>,
+}
+
+impl PackedMap {
+    pub fn parse(input: &str) -> Result<PackedMap, anyhow::Error> {
+        let mut width = 0;
+        let mut height = 0;
+        let mut bits = vec![];
+
+        for (i, r) in input.split('\n').enumerate() {
+            let r = r.trim_end().as_bytes();
+            if r.is_empty() {
+                continue;
+            }
+            if height == 0 {
+                width = r.len();
+            } else if r.len() != width {
+                return Err(anyhow!(
+                    "line {}: expected {} squares, found {}",
+                    i + 1,
+                    width,
+                    r.len()
+                ));
+            }
+
+            for chunk in r.chunks(64) {
+                let mut word = 0u64;
+                for (bit, c) in chunk.iter().enumerate() {
+                    match c {
+                        b'#' => word |= 1 << bit,
+                        b'.' => {}
+                        _ => return Err(anyhow!("line {}: only '.' and '#' can be packed", i + 1)),
+                    }
+                }
+                bits.push(word);
+            }
+            height += 1;
         }
+
+        Ok(PackedMap {
+            width,
+            stride: width.div_ceil(64),
+            bits,
+        })
     }
 
-    Ok(nr_of_trees)
+    // counts the trees on every slope in one pass over the rows, each slope
+    // keeps its own column and only looks at the rows it lands on
+    pub fn count_trees(&self, slopes: &[Slope]) -> Result<Vec<u64>, anyhow::Error> {
+        if let Some(slope) = slopes.iter().find(|s| s.dy == 0) {
+            return Err(anyhow!("slope {:?} never leaves the first row", slope));
+        }
+        let mut counts = vec![0; slopes.len()];
+        if self.width == 0 {
+            return Ok(counts);
+        }
+
+        let steps: Vec<usize> = slopes
+            .iter()
+            .map(|s| s.dx.rem_euclid(self.width as i64) as usize)
+            .collect();
+        let mut columns = vec![0; slopes.len()];
+
+        for (y, row) in self.bits.chunks(self.stride).enumerate() {
+            for (i, slope) in slopes.iter().enumerate() {
+                if y % slope.dy != 0 {
+                    continue;
+                }
+                let x = columns[i];
+                counts[i] += (row[x / 64] >> (x % 64)) & 1;
+                columns[i] = (x + steps[i]) % self.width;
+            }
+        }
+
+        Ok(counts)
+    }
+}
+
+// a random map with about a quarter trees, like the puzzle input
+pub fn generate_map<R: Rng>(rng: &mut R, rows: usize, width: usize) -> String {
+    let mut input = String::with_capacity(rows * (width + 1));
+    for _ in 0..rows {
+        for _ in 0..width {
+            input.push(if rng.gen_ratio(1, 4) { '#' } else { '.' });
+        }
+        input.push('\n');
+    }
+
+    input
+}
+
+// tree counts for every slope in the ranges, ordered by dy and then dx
+fn survey(
+    map: &Map,
+    dx: RangeInclusive<i64>,
+    dy: RangeInclusive<usize>,
+) -> Result<Vec<(Slope, u64)>, anyhow::Error> {
+    let mut counts = vec![];
+    for dy in dy {
+        for dx in dx.clone() {
+            let slope = Slope { dx, dy };
+            counts.push((slope, count_trees(map, slope)?));
+        }
+    }
+
+    Ok(counts)
+}
+
+const PART_1_SLOPE: Slope = Slope { dx: 3, dy: 1 };
+
+pub const PUZZLE_SLOPES: [Slope; 5] = [
+    Slope { dx: 1, dy: 1 },
+    Slope { dx: 3, dy: 1 },
+    Slope { dx: 5, dy: 1 },
+    Slope { dx: 7, dy: 1 },
+    Slope { dx: 1, dy: 2 },
+];
+
+// a square a route lands on, x keeps counting past the sides of the map so a
+// route going left stays left while column is where that is on the map
+struct Landing {
+    x: i64,
+    column: usize,
+    y: usize,
+    terrain: Terrain,
+}
+
+// the squares a slope lands on from the top left corner, stopping at a wall.
+// A route that wraps or is clamped past the bottom goes on until it would land
+// on a square a second time, from there on it would only repeat itself.
+fn route(map: &Map, slope: Slope) -> Result<Vec<Landing>, anyhow::Error> {
+    if slope.dy == 0 {
+        return Err(anyhow!("slope {:?} never leaves the first row", slope));
+    }
+
+    let mut landings = vec![];
+    let mut seen = HashSet::new();
+    for i in 0.. {
+        let (x, y) = (i * slope.dx, i * slope.dy as i64);
+        if y >= map.len() as i64 && map.ends_at_bottom() {
+            break;
+        }
+        let (column, row) = match (map.column(x)?, map.row(y)?) {
+            (Some(column), Some(row)) => (column, row),
+            _ => break,
+        };
+        if !map.ends_at_bottom() && !seen.insert((column, row)) {
+            break;
+        }
+
+        landings.push(Landing {
+            x,
+            column,
+            y: row,
+            terrain: map.rows[row][column],
+        });
+    }
+
+    Ok(landings)
+}
+
+// the map section covering every route, with the index of the last slope
+// landing on each square. A folded canvas puts every landing back onto one copy
+// of the map, so it stays as wide as the map however far the routes go.
+struct Canvas {
+    start: i64,
+    end: i64,
+    hits: Vec<Option<usize>>,
+}
+
+impl Canvas {
+    fn new(map: &Map, slopes: &[Slope], folded: bool) -> Result<Canvas, anyhow::Error> {
+        let width = map.width().max(1) as i64;
+        let routes = slopes
+            .iter()
+            .map(|s| route(map, *s))
+            .collect::<Result<Vec<Vec<Landing>>, anyhow::Error>>()?;
+
+        let (mut start, mut end) = (0, width);
+        if !folded {
+            for l in routes.iter().flatten() {
+                start = start.min(l.x.div_euclid(width) * width);
+                end = end.max((l.x.div_euclid(width) + 1) * width);
+            }
+        }
+
+        let columns = (end - start) as usize;
+        let mut hits = vec![None; columns * map.len()];
+        for (i, route) in routes.iter().enumerate() {
+            for l in route {
+                let x = if folded { l.column as i64 } else { l.x };
+                hits[l.y * columns + (x - start) as usize] = Some(i);
+            }
+        }
+
+        Ok(Canvas { start, end, hits })
+    }
+
+    fn hit(&self, x: i64, y: usize) -> Option<usize> {
+        self.hits[y * (self.end - self.start) as usize + (x - self.start) as usize]
+    }
+}
+
+// the map as in the puzzle statement, squares landed on are O when open and X on a tree.
+// Routes that go on past the bottom are folded, unfolded they would repeat the map every lap.
+fn render_ascii(map: &Map, slopes: &[Slope]) -> Result<String, anyhow::Error> {
+    let canvas = Canvas::new(map, slopes, !map.ends_at_bottom())?;
+
+    let mut out = String::new();
+    for y in 0..map.len() {
+        for x in canvas.start..canvas.end {
+            out.push(match (canvas.hit(x, y), map.terrain(x, y as i64)?) {
+                (_, None) => ' ',
+                (Some(_), Some(Terrain::Tree)) => 'X',
+                (Some(_), _) => 'O',
+                (None, Some(terrain)) => terrain.symbol(),
+            });
+        }
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+const OPEN: [u8; 3] = [240, 240, 230];
+const TREE: [u8; 3] = [40, 110, 50];
+const ROCK: [u8; 3] = [110, 100, 90];
+const ICE: [u8; 3] = [180, 220, 245];
+const WALL: [u8; 3] = [0, 0, 0];
+const ROUTES: [[u8; 3]; 6] = [
+    [220, 40, 40],
+    [40, 90, 220],
+    [230, 160, 20],
+    [150, 50, 190],
+    [20, 180, 190],
+    [120, 120, 120],
+];
+
+// a binary PPM with each square drawn as scale x scale pixels, every slope
+// gets its own colour and anything but open snow is drawn in a darker shade of it.
+// Routes are folded onto a single copy of the map to keep large maps drawable.
+fn write_ppm<W: Write>(
+    map: &Map,
+    slopes: &[Slope],
+    scale: usize,
+    out: &mut W,
+) -> Result<(), anyhow::Error> {
+    let canvas = Canvas::new(map, slopes, true)?;
+    let scale = scale.max(1);
+    let width = (canvas.end - canvas.start) as usize * scale;
+    let height = map.len() * scale;
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+
+    let mut line = Vec::with_capacity(width * 3);
+    for y in 0..map.len() {
+        line.clear();
+        for x in canvas.start..canvas.end {
+            let colour = match (canvas.hit(x, y), map.terrain(x, y as i64)?) {
+                (_, None) => WALL,
+                (Some(i), Some(Terrain::Open)) => ROUTES[i % ROUTES.len()],
+                (Some(i), Some(_)) => ROUTES[i % ROUTES.len()].map(|c| c / 2),
+                (None, Some(Terrain::Open)) => OPEN,
+                (None, Some(Terrain::Tree)) => TREE,
+                (None, Some(Terrain::Rock)) => ROCK,
+                (None, Some(Terrain::Ice)) => ICE,
+            };
+            for _ in 0..scale {
+                line.extend_from_slice(&colour);
+            }
+        }
+        for _ in 0..scale {
+            out.write_all(&line)?;
+        }
+    }
+
+    Ok(())
+}
+
+// ties go to the slope surveyed first
+fn fewest_trees(counts: &[(Slope, u64)]) -> Option<(Slope, u64)> {
+    counts.iter().copied().min_by_key(|(_, trees)| *trees)
 }
 
+#[cfg(test)]
 fn calc_2(map: &Map, d_x: &usize, d_y: &usize) -> Result<u64, anyhow::Error> {
     let mut nr_of_trees = 0;
     for (i, r) in (0..map.len()).step_by(*d_y).enumerate() {
-        if map.is_tree(i * d_x, r)? {
+        if map.terrain((i * d_x) as i64, r as i64)? == Some(Terrain::Tree) {
             nr_of_trees += 1;
         }
     }
@@ -73,6 +659,26 @@ fn calc_2(map: &Map, d_x: &usize, d_y: &usize) -> Result<u64, anyhow::Error> {
     Ok(nr_of_trees)
 }
 
+#[cfg(test)]
+fn seeded(seed: u64) -> rand::rngs::StdRng {
+    use rand::SeedableRng;
+
+    rand::rngs::StdRng::seed_from_u64(seed)
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#";
+
 #[test]
 fn part1() {
     let input = "..##.......
@@ -117,3 +723,245 @@ fn part2() {
 
     assert_eq!(336, result2);
 }
+
+#[test]
+fn test_count_trees_matches_calc_2() {
+    let map = parse_map(EXAMPLE).unwrap();
+
+    for (d_x, d_y) in &[(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)] {
+        let slope = Slope {
+            dx: *d_x as i64,
+            dy: *d_y,
+        };
+        assert_eq!(
+            calc_2(&map, d_x, d_y).unwrap(),
+            count_trees(&map, slope).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_negative_slope() {
+    let map = parse_map(EXAMPLE).unwrap();
+
+    // going left by 3 is the same as going right by width - 3
+    assert_eq!(
+        count_trees(&map, Slope { dx: 8, dy: 1 }).unwrap(),
+        count_trees(&map, Slope { dx: -3, dy: 1 }).unwrap()
+    );
+    assert_eq!(Slope { dx: -3, dy: 1 }, "-3,1".parse().unwrap());
+    assert!("1,0".parse::<Slope>().is_err());
+    assert!(count_trees(&map, Slope { dx: 1, dy: 0 }).is_err());
+}
+
+#[test]
+fn test_survey() {
+    let map = parse_map(EXAMPLE).unwrap();
+
+    let counts = survey(&map, 1..=7, 1..=2).unwrap();
+    assert_eq!(14, counts.len());
+    assert_eq!((Slope { dx: 3, dy: 1 }, 7), counts[2]);
+
+    let (slope, trees) = fewest_trees(&counts).unwrap();
+    assert_eq!(counts.iter().map(|(_, t)| *t).min().unwrap(), trees);
+    assert_eq!(trees, count_trees(&map, slope).unwrap());
+}
+
+#[test]
+fn test_render_ascii() {
+    let map = parse_map(EXAMPLE).unwrap();
+
+    let rendered = render_ascii(&map, &[Slope { dx: 3, dy: 1 }]).unwrap();
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert_eq!(11, lines.len());
+    assert_eq!("O.##.......", &lines[0][..11]);
+    assert_eq!("..##.......".repeat(2), &lines[0][11..]);
+    assert_eq!("#..O#...#..", &lines[1][..11]);
+    assert_eq!(
+        "..#.#...#.#".repeat(3),
+        lines[3].replace('X', "#").replace('O', ".")
+    );
+    assert_eq!(7, rendered.matches('X').count());
+    assert_eq!(4, rendered.matches('O').count());
+}
+
+#[test]
+fn test_render_going_left() {
+    let map = parse_map("#..\n.#.\n..#").unwrap();
+
+    let rendered = render_ascii(&map, &[Slope { dx: -1, dy: 1 }]).unwrap();
+
+    assert_eq!("#..X..\n.#O.#.\n.O#..#\n", rendered);
+}
+
+#[test]
+fn test_write_ppm() {
+    let map = parse_map(EXAMPLE).unwrap();
+
+    let mut out = vec![];
+    write_ppm(&map, &PUZZLE_SLOPES, 2, &mut out).unwrap();
+
+    // routes are folded onto the 11 wide map instead of repeating it
+    let header = "P6\n22 22\n255\n";
+    assert!(out.starts_with(header.as_bytes()));
+    assert_eq!(header.len() + 22 * 22 * 3, out.len());
+    // every slope starts on the open top left square, the last one drawn wins
+    assert_eq!(&ROUTES[4], &out[header.len()..header.len() + 3]);
+    // the 3 right slope wraps onto the tree at column 4 of row 5, drawn darker
+    let pixel = header.len() + (5 * 2 * 22 + 4 * 2) * 3;
+    assert_eq!(&ROUTES[1].map(|c| c / 2), &out[pixel..pixel + 3]);
+}
+
+#[test]
+fn test_ppm_width_stays_linear() {
+    let input = generate_map(&mut seeded(3), 2000, 31);
+    let map = parse_map(&input).unwrap();
+
+    let mut out = vec![];
+    write_ppm(&map, &[Slope { dx: 7, dy: 1 }], 1, &mut out).unwrap();
+
+    let header = "P6\n31 2000\n255\n";
+    assert!(out.starts_with(header.as_bytes()));
+    assert_eq!(header.len() + 31 * 2000 * 3, out.len());
+}
+
+#[test]
+fn test_outside_of_map_is_an_error() {
+    let map = parse_map(EXAMPLE).unwrap();
+
+    assert!(map.terrain(0, 10).is_ok());
+    assert!(map.terrain(0, 11).is_err());
+    assert!(map.terrain(0, 12).is_err());
+}
+
+#[test]
+fn test_edges() {
+    let map = parse_map("#..\n.#.\n..#").unwrap();
+    let right = Slope { dx: 1, dy: 1 };
+    let steep = Slope { dx: 2, dy: 1 };
+
+    assert_eq!(1, count_trees(&map, steep).unwrap());
+
+    let clamped = map.with_edge(Edge::Clamp);
+    assert_eq!(Some(Terrain::Tree), clamped.terrain(-4, 0).unwrap());
+    assert_eq!(Some(Terrain::Open), clamped.terrain(-4, 1).unwrap());
+    assert_eq!(Some(Terrain::Tree), clamped.terrain(9, 2).unwrap());
+    assert_eq!(2, count_trees(&clamped, steep).unwrap());
+
+    let walled = clamped.with_edge(Edge::Wall);
+    assert_eq!(None, walled.terrain(3, 0).unwrap());
+    assert_eq!(None, walled.terrain(-1, 0).unwrap());
+    assert!(walled.terrain(0, 3).is_err());
+    assert_eq!(1, count_trees(&walled, steep).unwrap());
+    assert_eq!(3, count_trees(&walled, right).unwrap());
+
+    let strict = walled.with_edge(Edge::Error);
+    assert!(count_trees(&strict, steep).is_err());
+    assert_eq!(3, count_trees(&strict, right).unwrap());
+}
+
+#[test]
+fn test_vertical_edges() {
+    let map = parse_map("..#\n..#\n.#.").unwrap();
+    let slope = Slope { dx: 1, dy: 2 };
+
+    assert!(map.terrain(0, 3).is_err());
+    assert!(map.terrain(0, -1).is_err());
+    assert_eq!(1, count_trees(&map, slope).unwrap());
+
+    let walled = map.with_edge_y(Edge::Wall);
+    assert_eq!(None, walled.terrain(0, 3).unwrap());
+    assert_eq!(1, count_trees(&walled, slope).unwrap());
+
+    // wrapping goes on from the top until the route comes back to the corner
+    let wrapped = walled.with_edge_y(Edge::Wrap);
+    assert_eq!(Some(Terrain::Open), wrapped.terrain(0, -1).unwrap());
+    assert_eq!(Some(Terrain::Tree), wrapped.terrain(2, 4).unwrap());
+    assert_eq!(3, route(&wrapped, slope).unwrap().len());
+    assert_eq!(2, count_trees(&wrapped, slope).unwrap());
+    assert_eq!("O.#\n..X\n.X.\n", render_ascii(&wrapped, &[slope]).unwrap());
+
+    // clamping slides along the last row until a square comes round again
+    let clamped = wrapped.with_edge_y(Edge::Clamp);
+    assert_eq!(Some(Terrain::Tree), clamped.terrain(1, 9).unwrap());
+    assert_eq!(4, route(&clamped, slope).unwrap().len());
+    assert_eq!(1, count_trees(&clamped, slope).unwrap());
+
+    // a wall on the side still ends a route that would go on past the bottom
+    let boxed = clamped.with_edge(Edge::Wall);
+    assert_eq!(3, route(&boxed, slope).unwrap().len());
+}
+
+#[test]
+fn test_render_stops_at_walls() {
+    let map = parse_map("#..\n.#.\n..#").unwrap().with_edge(Edge::Wall);
+    let steep = [Slope { dx: 2, dy: 1 }];
+
+    assert_eq!("X..\n.#O\n..#\n", render_ascii(&map, &steep).unwrap());
+
+    let mut out = vec![];
+    write_ppm(&map, &steep, 1, &mut out).unwrap();
+    let header = "P6\n3 3\n255\n";
+    let last_row = &out[header.len() + 2 * 3 * 3..];
+    assert_eq!([OPEN, OPEN, TREE].concat(), last_row);
+
+    let strict = map.with_edge(Edge::Error);
+    assert!(render_ascii(&strict, &steep).is_err());
+}
+
+#[test]
+fn test_weighted_route() {
+    let map = parse_map("#.^\n~#.\n.^#\n~..").unwrap();
+    let costs: Costs = "rock=5, ice=2".parse().unwrap();
+
+    assert_eq!(1, costs.tree);
+    assert_eq!(5, route_cost(&map, Slope { dx: 1, dy: 1 }, &costs).unwrap());
+    assert_eq!(8, route_cost(&map, Slope { dx: 2, dy: 1 }, &costs).unwrap());
+    assert!("lava=3".parse::<Costs>().is_err());
+}
+
+#[test]
+fn test_parse_terrain_errors() {
+    let err = parse_map("..#\n.x.").unwrap_err();
+    assert_eq!("line 2, column 2: unknown terrain 'x'", err.to_string());
+
+    let err = parse_map("..#\n....").unwrap_err();
+    assert_eq!("line 2: expected 3 squares, found 4", err.to_string());
+}
+
+#[test]
+fn test_packed_map() {
+    let packed = PackedMap::parse(EXAMPLE).unwrap();
+
+    assert_eq!(
+        (11, 1, 11),
+        (packed.width, packed.stride, packed.bits.len())
+    );
+    assert_eq!(
+        vec![2, 7, 3, 4, 2],
+        packed.count_trees(&PUZZLE_SLOPES).unwrap()
+    );
+    assert!(packed.count_trees(&[Slope { dx: 1, dy: 0 }]).is_err());
+    assert!(PackedMap::parse("..^").is_err());
+}
+
+#[test]
+fn test_packed_map_matches_map() {
+    // wider than a word so rows span several of them
+    let input = generate_map(&mut seeded(7), 500, 155);
+    let map = parse_map(&input).unwrap();
+    let packed = PackedMap::parse(&input).unwrap();
+    assert_eq!(3, packed.stride);
+
+    let slopes: Vec<Slope> = (-70..70)
+        .step_by(3)
+        .flat_map(|dx| (1..4).map(move |dy| Slope { dx, dy }))
+        .collect();
+    let expected: Vec<u64> = slopes
+        .iter()
+        .map(|s| count_trees(&map, *s).unwrap())
+        .collect();
+
+    assert_eq!(expected, packed.count_trees(&slopes).unwrap());
+}
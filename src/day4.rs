@@ -1,24 +1,92 @@
+use crate::report::ReportFormat;
+use crate::schema::{Diagnosis, Schema};
 use anyhow::anyhow;
-use nom::branch::alt;
-use nom::bytes::complete::{tag, take_while};
-use nom::combinator::map_res;
-use nom::lib::std::collections::HashMap;
-use nom::sequence::tuple;
-use nom::{AsChar, IResult};
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::alphanumeric1;
+use nom::combinator::{all_consuming, recognize};
+use nom::sequence::{pair, tuple};
+use nom::IResult;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
+#[derive(Clone, Debug, Serialize)]
 struct Passport {
+    line: usize,
     ecl: Option<String>,
     pid: Option<String>,
     eyr: Option<String>,
     hcl: Option<String>,
     byr: Option<String>,
     iyr: Option<String>,
-    #[allow(dead_code)]
     cid: Option<String>,
     hgt: Option<String>,
+    extra: BTreeMap<String, String>,
+    duplicates: Vec<String>,
 }
 
 impl Passport {
+    fn new(line: usize) -> Passport {
+        Passport {
+            line,
+            ecl: None,
+            pid: None,
+            eyr: None,
+            hcl: None,
+            byr: None,
+            iyr: None,
+            cid: None,
+            hgt: None,
+            extra: BTreeMap::new(),
+            duplicates: vec![],
+        }
+    }
+
+    // the first value given wins, later ones are only flagged
+    fn set(&mut self, name: &str, value: &str) {
+        let slot = match name {
+            "ecl" => &mut self.ecl,
+            "pid" => &mut self.pid,
+            "eyr" => &mut self.eyr,
+            "hcl" => &mut self.hcl,
+            "byr" => &mut self.byr,
+            "iyr" => &mut self.iyr,
+            "cid" => &mut self.cid,
+            "hgt" => &mut self.hgt,
+            _ => {
+                if self.extra.contains_key(name) {
+                    self.duplicates.push(name.to_owned());
+                } else {
+                    self.extra.insert(name.to_owned(), value.to_owned());
+                }
+                return;
+            }
+        };
+        match slot {
+            Some(_) => self.duplicates.push(name.to_owned()),
+            None => *slot = Some(value.to_owned()),
+        }
+    }
+
+    // unknown fields are kept too, so a schema can check them
+    fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "ecl" => self.ecl.as_deref(),
+            "pid" => self.pid.as_deref(),
+            "eyr" => self.eyr.as_deref(),
+            "hcl" => self.hcl.as_deref(),
+            "byr" => self.byr.as_deref(),
+            "iyr" => self.iyr.as_deref(),
+            "cid" => self.cid.as_deref(),
+            "hgt" => self.hgt.as_deref(),
+            _ => self.extra.get(name).map(|v| v.as_str()),
+        }
+    }
+
     fn is_valid(&self) -> bool {
         self.ecl.is_some()
             && self.pid.is_some()
@@ -28,191 +96,537 @@ impl Passport {
             && self.iyr.is_some()
             && self.hgt.is_some()
     }
+}
 
-    fn validate_all_fields(&self) -> bool {
-        validate_ecl(&self.ecl)
-            && validate_pid(&self.pid)
-            && validate_eyr(&self.eyr)
-            && validate_hcl(&self.hcl)
-            && validate_byr(&self.byr)
-            && validate_iyr(&self.iyr)
-            && validate_hgt(&self.hgt)
+// the typed parsers below only check a value's shape, which values are
+// allowed is up to the rules in res/day4-schema.toml
+fn parse_year(value: &str) -> Result<u16, anyhow::Error> {
+    if value.len() != 4 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(anyhow!("{} is not a 4 digit year", value));
     }
+
+    Ok(value.parse()?)
 }
 
-fn validate_hgt(hgt: &Option<String>) -> bool {
-    match hgt {
-        Some(hgt) => {
-            if hgt.ends_with("in") {
-                let l = hgt[..hgt.len() - 2].parse::<i32>().unwrap();
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum HeightUnit {
+    #[serde(rename = "cm")]
+    Centimetres,
+    #[serde(rename = "in")]
+    Inches,
+}
 
-                return (59..=76).contains(&l);
-            }
-            if hgt.ends_with("cm") {
-                let l = hgt[..hgt.len() - 2].parse::<i32>().unwrap();
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Height {
+    pub value: u32,
+    pub unit: HeightUnit,
+}
 
-                return (150..=193).contains(&l);
-            }
-            false
+impl FromStr for Height {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Height, anyhow::Error> {
+        let (number, unit) = if let Some(number) = s.strip_suffix("cm") {
+            (number, HeightUnit::Centimetres)
+        } else if let Some(number) = s.strip_suffix("in") {
+            (number, HeightUnit::Inches)
+        } else {
+            return Err(anyhow!("{} needs a unit, cm or in", s));
+        };
+
+        if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(anyhow!("{} is not a number of {}", s, &s[number.len()..]));
         }
-        None => false,
+
+        Ok(Height {
+            value: number.parse()?,
+            unit,
+        })
     }
 }
 
-fn validate_iyr(iyr: &Option<String>) -> bool {
-    match iyr {
-        Some(iyr) => {
-            if iyr.len() != 4 || iyr.chars().take_while(|c| c.is_numeric()).count() != 4 {
-                return false;
-            }
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl FromStr for Rgb {
+    type Err = anyhow::Error;
+
+    // lowercase only, as the puzzle asks for
+    fn from_str(s: &str) -> Result<Rgb, anyhow::Error> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|h| h.len() == 6 && h.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')))
+            .ok_or_else(|| anyhow!("{} is not a # followed by 6 lowercase hex digits", s))?;
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+
+        Ok(Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EyeColour {
+    Amber,
+    Blue,
+    Brown,
+    Grey,
+    Green,
+    Hazel,
+    Other,
+}
+
+impl FromStr for EyeColour {
+    type Err = anyhow::Error;
 
-            let year = iyr.parse::<i32>().unwrap();
-            (2010..=2020).contains(&year)
+    fn from_str(s: &str) -> Result<EyeColour, anyhow::Error> {
+        match s {
+            "amb" => Ok(EyeColour::Amber),
+            "blu" => Ok(EyeColour::Blue),
+            "brn" => Ok(EyeColour::Brown),
+            "gry" => Ok(EyeColour::Grey),
+            "grn" => Ok(EyeColour::Green),
+            "hzl" => Ok(EyeColour::Hazel),
+            "oth" => Ok(EyeColour::Other),
+            _ => Err(anyhow!(
+                "{} is not one of amb, blu, brn, gry, grn, hzl, oth",
+                s
+            )),
         }
-        None => false,
     }
 }
 
-fn validate_byr(byr: &Option<String>) -> bool {
-    match byr {
-        Some(byr) => {
-            if byr.len() != 4 || byr.chars().take_while(|c| c.is_numeric()).count() != 4 {
-                return false;
-            }
+// exactly nine digits, leading zeros included when printed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PassportId(u32);
+
+impl FromStr for PassportId {
+    type Err = anyhow::Error;
 
-            let year = byr.parse::<i32>().unwrap();
-            (1920..=2002).contains(&year)
+    fn from_str(s: &str) -> Result<PassportId, anyhow::Error> {
+        if s.len() != 9 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(anyhow!("{} is not 9 digits", s));
         }
-        None => false,
+        Ok(PassportId(s.parse()?))
     }
 }
 
-fn validate_hcl(hcl: &Option<String>) -> bool {
-    match hcl {
-        Some(hcl) => {
-            if !hcl.starts_with('#') {
-                return false;
-            }
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:09}", self.0)
+    }
+}
 
-            hcl.chars().skip(1).take_while(|c| c.is_hex_digit()).count() == 6
+// as the nine digit string, a number would lose the leading zeros
+impl Serialize for PassportId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ValidatedPassport {
+    pub line: usize,
+    pub birth_year: u16,
+    pub issue_year: u16,
+    pub expiration_year: u16,
+    pub height: Height,
+    pub hair_colour: Rgb,
+    pub eye_colour: EyeColour,
+    pub passport_id: PassportId,
+    pub country_id: Option<String>,
+}
+
+// the first missing field or else the first broken one, prefixed with the passport's line
+fn first_problem(line: usize, diagnosis: &Diagnosis) -> Option<String> {
+    match (diagnosis.missing.first(), diagnosis.invalid.first()) {
+        (Some(name), _) => Some(format!("line {}: {} missing", line, name)),
+        (None, Some(problem)) => Some(format!("line {}: {}", line, problem.reason)),
+        (None, None) => None,
+    }
+}
+
+// checked against the shipped schema first, so its first problem is reported
+impl TryFrom<Passport> for ValidatedPassport {
+    type Error = anyhow::Error;
+
+    fn try_from(p: Passport) -> Result<ValidatedPassport, anyhow::Error> {
+        let line = p.line;
+        let diagnosis = passport_schema().diagnose(|name| p.field(name));
+        if let Some(problem) = first_problem(line, &diagnosis) {
+            return Err(anyhow!(problem));
         }
-        None => false,
+
+        // the schema requires every field but cid, so they are all there by now
+        let field = |value: Option<String>| value.expect("required by the shipped schema");
+        let explain =
+            |name: &'static str| move |e: anyhow::Error| anyhow!("line {}: {} {}", line, name, e);
+
+        Ok(ValidatedPassport {
+            line,
+            birth_year: parse_year(&field(p.byr)).map_err(explain("byr"))?,
+            issue_year: parse_year(&field(p.iyr)).map_err(explain("iyr"))?,
+            expiration_year: parse_year(&field(p.eyr)).map_err(explain("eyr"))?,
+            height: field(p.hgt).parse().map_err(explain("hgt"))?,
+            hair_colour: field(p.hcl).parse().map_err(explain("hcl"))?,
+            eye_colour: field(p.ecl).parse().map_err(explain("ecl"))?,
+            passport_id: field(p.pid).parse().map_err(explain("pid"))?,
+            country_id: p.cid,
+        })
     }
 }
 
-fn validate_eyr(eyr: &Option<String>) -> bool {
-    match eyr {
-        Some(eyr) => {
-            if eyr.len() != 4 || eyr.chars().take_while(|c| c.is_numeric()).count() != 4 {
-                return false;
+#[derive(Debug, PartialEq, Serialize)]
+struct PassportReport {
+    line: usize,
+    valid: bool,
+    duplicates: Vec<String>,
+    #[serde(flatten)]
+    diagnosis: Diagnosis,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct ProblemRow<'a> {
+    line: usize,
+    field: &'a str,
+    value: &'a str,
+    reason: String,
+}
+
+fn diagnose(passports: &[Passport], schema: &Schema) -> Vec<PassportReport> {
+    passports
+        .iter()
+        .map(|p| {
+            let diagnosis = schema.diagnose(|name| p.field(name));
+            PassportReport {
+                line: p.line,
+                valid: diagnosis.is_valid(),
+                duplicates: p.duplicates.clone(),
+                diagnosis,
             }
+        })
+        .collect()
+}
 
-            let year = eyr.parse::<i32>().unwrap();
-            (2020..=2030).contains(&year)
+// text and csv get a line per problem, json a record per passport
+fn write_report<W: Write>(
+    reports: &[PassportReport],
+    format: ReportFormat,
+    mut out: W,
+) -> Result<(), anyhow::Error> {
+    match format {
+        ReportFormat::Text => {
+            for report in reports {
+                if report.valid {
+                    writeln!(out, "line {}: valid", report.line)?;
+                }
+                for field in &report.diagnosis.missing {
+                    writeln!(out, "line {}: {} missing", report.line, field)?;
+                }
+                for problem in &report.diagnosis.invalid {
+                    writeln!(out, "line {}: {}", report.line, problem.reason)?;
+                }
+                for field in &report.duplicates {
+                    writeln!(out, "line {}: {} given more than once", report.line, field)?;
+                }
+            }
+        }
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, reports)?;
+            writeln!(out)?;
+        }
+        ReportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for report in reports {
+                for field in &report.diagnosis.missing {
+                    writer.serialize(ProblemRow {
+                        line: report.line,
+                        field,
+                        value: "",
+                        reason: format!("{} missing", field),
+                    })?;
+                }
+                for problem in &report.diagnosis.invalid {
+                    writer.serialize(ProblemRow {
+                        line: report.line,
+                        field: &problem.field,
+                        value: &problem.value,
+                        reason: problem.reason.clone(),
+                    })?;
+                }
+                for field in &report.duplicates {
+                    writer.serialize(ProblemRow {
+                        line: report.line,
+                        field,
+                        value: "",
+                        reason: format!("{} given more than once", field),
+                    })?;
+                }
+            }
+            writer.flush()?;
         }
-        None => false,
     }
+
+    Ok(())
 }
 
-fn validate_pid(pid: &Option<String>) -> bool {
-    match pid {
-        Some(pid) => pid.len() == 9 && pid.chars().take_while(|c| c.is_numeric()).count() == 9,
-        None => false,
-    }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    JsonLines,
+    Csv,
 }
 
-fn validate_ecl(ecl: &Option<String>) -> bool {
-    match ecl {
-        Some(ecl) => {
-            matches!(
-                &ecl[..],
-                "amb" | "blu" | "brn" | "gry" | "grn" | "hzl" | "oth"
-            )
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ExportFormat, anyhow::Error> {
+        match s {
+            "jsonl" => Ok(ExportFormat::JsonLines),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(anyhow!("export format must be one of jsonl or csv")),
         }
-        None => false,
     }
 }
 
-pub fn run() -> Result<(String, String), anyhow::Error> {
-    let input: String = std::fs::read_to_string("res/day4-input")?.parse()?;
+#[derive(Serialize)]
+struct ExportRecord<'a> {
+    #[serde(flatten)]
+    passport: &'a Passport,
+    complete: bool,
+    valid: bool,
+    error: Option<String>,
+    validated: Option<ValidatedPassport>,
+}
 
-    let passports = parse_passports(&input)?;
+// csv has no nesting, so unknown fields and duplicates are joined into one column each
+#[derive(Serialize)]
+struct ExportRow<'a> {
+    line: usize,
+    byr: Option<&'a str>,
+    iyr: Option<&'a str>,
+    eyr: Option<&'a str>,
+    hgt: Option<&'a str>,
+    hcl: Option<&'a str>,
+    ecl: Option<&'a str>,
+    pid: Option<&'a str>,
+    cid: Option<&'a str>,
+    extra: String,
+    duplicates: String,
+    complete: bool,
+    valid: bool,
+    error: Option<String>,
+}
 
-    let result_1 = passports.iter().filter(|p| p.is_valid()).count();
-    let result_2 = passports.iter().filter(|p| p.validate_all_fields()).count();
+// what the export says about one passport
+struct Verdict {
+    complete: bool,
+    valid: bool,
+    error: Option<String>,
+    validated: Option<ValidatedPassport>,
+}
 
-    Ok((format!("{}", result_1), format!("{}", result_2)))
+// a custom schema has no typed passport to go with it, only the puzzle's rules do
+fn check(p: &Passport, schema: Option<&Schema>) -> Verdict {
+    match schema {
+        Some(schema) => {
+            let diagnosis = schema.diagnose(|name| p.field(name));
+            Verdict {
+                complete: schema.has_required(|name| p.field(name)),
+                valid: diagnosis.is_valid(),
+                error: first_problem(p.line, &diagnosis),
+                validated: None,
+            }
+        }
+        None => match ValidatedPassport::try_from(p.clone()) {
+            Ok(validated) => Verdict {
+                complete: true,
+                valid: true,
+                error: None,
+                validated: Some(validated),
+            },
+            Err(e) => Verdict {
+                complete: p.is_valid(),
+                valid: false,
+                error: Some(e.to_string()),
+                validated: None,
+            },
+        },
+    }
 }
 
-fn not_space_or_newline(c: char) -> bool {
-    c != '\n' && c != ' '
+// every passport with whether it has the required fields and whether it
+// passes the rules of the given schema or else the puzzle's, and why not
+fn export<W: Write>(
+    passports: &[Passport],
+    schema: Option<&Schema>,
+    format: ExportFormat,
+    mut out: W,
+) -> Result<(), anyhow::Error> {
+    match format {
+        ExportFormat::JsonLines => {
+            for p in passports {
+                let verdict = check(p, schema);
+                let record = ExportRecord {
+                    passport: p,
+                    complete: verdict.complete,
+                    valid: verdict.valid,
+                    error: verdict.error,
+                    validated: verdict.validated,
+                };
+                serde_json::to_writer(&mut out, &record)?;
+                writeln!(out)?;
+            }
+            out.flush()?;
+        }
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for p in passports {
+                let verdict = check(p, schema);
+                writer.serialize(ExportRow {
+                    line: p.line,
+                    byr: p.byr.as_deref(),
+                    iyr: p.iyr.as_deref(),
+                    eyr: p.eyr.as_deref(),
+                    hgt: p.hgt.as_deref(),
+                    hcl: p.hcl.as_deref(),
+                    ecl: p.ecl.as_deref(),
+                    pid: p.pid.as_deref(),
+                    cid: p.cid.as_deref(),
+                    extra: p
+                        .extra
+                        .iter()
+                        .map(|(k, v)| format!("{}:{}", k, v))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    duplicates: p.duplicates.join(" "),
+                    complete: verdict.complete,
+                    valid: verdict.valid,
+                    error: verdict.error,
+                })?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
 }
 
-fn from_str(input: &str) -> Result<&str, std::num::ParseIntError> {
-    Ok(input)
+// the puzzle's rules, compiled once since every ValidatedPassport is checked against them
+fn passport_schema() -> &'static Schema {
+    static SCHEMA: OnceLock<Schema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        Schema::from_toml(include_str!("../res/day4-schema.toml"))
+            .expect("res/day4-schema.toml is a valid schema")
+    })
 }
 
-fn string(input: &str) -> IResult<&str, &str> {
-    map_res(take_while(not_space_or_newline), from_str)(input)
+// a schema file replaces the built in rules for both parts, reports
+// explain every passport against it or against the puzzle's rules
+pub fn run(
+    schema: Option<&str>,
+    report_format: Option<ReportFormat>,
+    export_format: Option<ExportFormat>,
+) -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day4-input")?.parse()?;
+
+    let mut passports = vec![];
+    for record in parse_batch(&input) {
+        match record {
+            Ok(passport) => passports.push(passport),
+            Err(e) => eprintln!("skipping passport, {}", e),
+        }
+    }
+
+    let loaded = schema.map(Schema::load).transpose()?;
+
+    if let Some(format) = report_format {
+        let schema = loaded.as_ref().unwrap_or_else(|| passport_schema());
+        let stdout = std::io::stdout();
+        write_report(&diagnose(&passports, schema), format, stdout.lock())?;
+    }
+
+    if let Some(format) = export_format {
+        let stdout = std::io::stdout();
+        export(&passports, loaded.as_ref(), format, stdout.lock())?;
+    }
+
+    let (result_1, result_2) = match loaded {
+        Some(schema) => (
+            passports
+                .iter()
+                .filter(|p| schema.has_required(|name| p.field(name)))
+                .count(),
+            passports
+                .iter()
+                .filter(|p| schema.is_valid(|name| p.field(name)))
+                .count(),
+        ),
+        None => (
+            passports.iter().filter(|p| p.is_valid()).count(),
+            passports
+                .into_iter()
+                .filter_map(|p| ValidatedPassport::try_from(p).ok())
+                .count(),
+        ),
+    };
+
+    Ok((format!("{}", result_1), format!("{}", result_2)))
 }
 
 fn passport_tuple(input: &str) -> IResult<&str, (&str, &str)> {
-    alt((
-        tuple((tag("ecl:"), string)),
-        tuple((tag("pid:"), string)),
-        tuple((tag("eyr:"), string)),
-        tuple((tag("hcl:"), string)),
-        tuple((tag("byr:"), string)),
-        tuple((tag("iyr:"), string)),
-        tuple((tag("cid:"), string)),
-        tuple((tag("hgt:"), string)),
+    tuple((
+        recognize(pair(alphanumeric1, tag(":"))),
+        take_while1(|c: char| !c.is_whitespace()),
     ))(input)
 }
 
-fn end_or_space(input: &str) -> IResult<&str, &str> {
-    alt((tag("\n\n"), tag("\n"), tag(" "), tag("")))(input)
+fn parse_record(start: usize, lines: &[(usize, &str)]) -> Result<Passport, anyhow::Error> {
+    let mut passport = Passport::new(start);
+    for (number, line) in lines {
+        for token in line.split_whitespace() {
+            let (_, (key, value)) = all_consuming(passport_tuple)(token)
+                .map_err(|_| anyhow!("line {}: malformed field '{}'", number, token))?;
+            passport.set(&key[..key.len() - 1], value);
+        }
+    }
+
+    Ok(passport)
 }
 
-fn parse_passports(input: &str) -> Result<Vec<Passport>, anyhow::Error> {
-    let mut end = false;
-    let mut passports: Vec<Passport> = vec![];
-    let mut state = HashMap::new();
-    let mut i = input;
-    while !end {
-        let (input, (key, val)) = passport_tuple(i).map_err(|e| anyhow!(format!("{:?}", e)))?;
-        state.insert(key, val.to_owned());
-        let (input, e_or_s) = end_or_space(input).map_err(|e| anyhow!(format!("{:?}", e)))?;
-        i = input;
-        if e_or_s == "\n\n" {
-            passports.push(Passport {
-                ecl: state.get("ecl:").cloned(),
-                pid: state.get("pid:").cloned(),
-                eyr: state.get("eyr:").cloned(),
-                hcl: state.get("hcl:").cloned(),
-                byr: state.get("byr:").cloned(),
-                iyr: state.get("iyr:").cloned(),
-                cid: state.get("cid:").cloned(),
-                hgt: state.get("hgt:").cloned(),
-            });
-
-            state.clear();
-        }
-        if i.is_empty() {
-            passports.push(Passport {
-                ecl: state.get("ecl:").cloned(),
-                pid: state.get("pid:").cloned(),
-                eyr: state.get("eyr:").cloned(),
-                hcl: state.get("hcl:").cloned(),
-                byr: state.get("byr:").cloned(),
-                iyr: state.get("iyr:").cloned(),
-                cid: state.get("cid:").cloned(),
-                hgt: state.get("hgt:").cloned(),
-            });
-            end = true;
+// records are separated by lines holding nothing but whitespace, each is
+// parsed on its own so one bad record doesn't sink the batch
+fn parse_batch(input: &str) -> Vec<Result<Passport, anyhow::Error>> {
+    let mut records = vec![];
+    let mut current: Option<(usize, Vec<(usize, &str)>)> = None;
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            if let Some((start, lines)) = current.take() {
+                records.push(parse_record(start, &lines));
+            }
+        } else {
+            current
+                .get_or_insert_with(|| (i + 1, vec![]))
+                .1
+                .push((i + 1, line));
         }
     }
+    if let Some((start, lines)) = current {
+        records.push(parse_record(start, &lines));
+    }
 
-    Ok(passports)
+    records
+}
+
+#[cfg(test)]
+fn parse_passports(input: &str) -> Result<Vec<Passport>, anyhow::Error> {
+    parse_batch(input).into_iter().collect()
 }
 
 #[test]
@@ -245,67 +659,102 @@ iyr:2011 ecl:brn hgt:59in";
 
 #[test]
 fn byr_valid() {
-    assert_eq!(true, validate_byr(&Some("2002".to_string())))
+    assert!(ValidatedPassport::try_from(valid_except("byr", "2002")).is_ok())
 }
 
 #[test]
 fn byr_invalid() {
-    assert_eq!(false, validate_byr(&Some("2003".to_string())))
+    assert_eq!(
+        "line 1: byr 2003 out of range 1920..=2002",
+        ValidatedPassport::try_from(valid_except("byr", "2003"))
+            .unwrap_err()
+            .to_string()
+    )
 }
 
 #[test]
 fn hgt_valid1() {
-    assert_eq!(true, validate_hgt(&Some("60in".to_string())))
+    assert!(ValidatedPassport::try_from(valid_except("hgt", "60in")).is_ok())
 }
 
 #[test]
 fn hgt_valid2() {
-    assert_eq!(true, validate_hgt(&Some("190cm".to_string())))
+    assert!(ValidatedPassport::try_from(valid_except("hgt", "190cm")).is_ok())
 }
 
 #[test]
 fn hgt_invalid1() {
-    assert_eq!(false, validate_hgt(&Some("190in".to_string())))
+    assert_eq!(
+        "line 1: hgt 190in out of range 59..=76",
+        ValidatedPassport::try_from(valid_except("hgt", "190in"))
+            .unwrap_err()
+            .to_string()
+    )
 }
 
 #[test]
 fn hgt_invalid2() {
-    assert_eq!(false, validate_hgt(&Some("190".to_string())))
+    assert_eq!(
+        "line 1: hgt 190 needs a unit, one of cm, in",
+        ValidatedPassport::try_from(valid_except("hgt", "190"))
+            .unwrap_err()
+            .to_string()
+    )
 }
 
 #[test]
 fn hcl_valid() {
-    assert_eq!(true, validate_hcl(&Some("#123abc".to_string())))
+    assert!(ValidatedPassport::try_from(valid_except("hcl", "#123abc")).is_ok())
 }
 
 #[test]
 fn hcl_invalid1() {
-    assert_eq!(false, validate_byr(&Some("#123abz".to_string())))
+    assert_eq!(
+        "line 1: hcl #123abz doesn't match ^#[0-9a-f]{6}$",
+        ValidatedPassport::try_from(valid_except("hcl", "#123abz"))
+            .unwrap_err()
+            .to_string()
+    )
 }
 
 #[test]
 fn hcl_invalid2() {
-    assert_eq!(false, validate_byr(&Some("123abc".to_string())))
+    assert_eq!(
+        "line 1: hcl 123abc doesn't match ^#[0-9a-f]{6}$",
+        ValidatedPassport::try_from(valid_except("hcl", "123abc"))
+            .unwrap_err()
+            .to_string()
+    )
 }
 
 #[test]
 fn ecl_valid() {
-    assert_eq!(true, validate_ecl(&Some("brn".to_string())))
+    assert!(ValidatedPassport::try_from(valid_except("ecl", "brn")).is_ok())
 }
 
 #[test]
 fn ecl_invalid() {
-    assert_eq!(false, validate_ecl(&Some("wat".to_string())))
+    assert_eq!(
+        "line 1: ecl wat is not one of amb, blu, brn, gry, grn, hzl, oth",
+        ValidatedPassport::try_from(valid_except("ecl", "wat"))
+            .unwrap_err()
+            .to_string()
+    )
 }
 
 #[test]
 fn pid_valid() {
-    assert_eq!(true, validate_pid(&Some("000000001".to_string())))
+    assert!(ValidatedPassport::try_from(valid_except("pid", "000000001")).is_ok())
 }
 
 #[test]
 fn pid_invalid() {
-    assert_eq!(false, validate_pid(&Some("0123456789".to_string())))
+    assert_eq!(
+        "line 1: pid 0123456789 doesn't match ^[0-9]{9}$",
+        ValidatedPassport::try_from(valid_except("pid", "0123456789"))
+            .unwrap_err()
+            .to_string()
+    )
 }
 
 #[test]
@@ -325,10 +774,13 @@ eyr:2038 hcl:74454a iyr:2023
 pid:3556412378 byr:2007";
 
     let passports = parse_passports(&input).unwrap();
+    assert_eq!(4, passports.len());
 
-    let result_1 = passports.iter().filter(|p| p.validate_all_fields()).count();
+    let result_1 = passports
+        .into_iter()
+        .filter_map(|p| ValidatedPassport::try_from(p).ok())
+        .count();
 
-    assert_eq!(4, passports.len());
     assert_eq!(0, result_1);
 }
 
@@ -348,9 +800,466 @@ eyr:2022
 iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719";
 
     let passports = parse_passports(&input).unwrap();
+    assert_eq!(4, passports.len());
 
-    let result_1 = passports.iter().filter(|p| p.validate_all_fields()).count();
+    let result_1 = passports
+        .into_iter()
+        .filter_map(|p| ValidatedPassport::try_from(p).ok())
+        .count();
 
-    assert_eq!(4, passports.len());
     assert_eq!(4, result_1);
 }
+
+#[test]
+fn test_schema_matches_built_in_rules() {
+    let schema = Schema::load("res/day4-schema.toml").unwrap();
+    let input = "eyr:1972 cid:100
+hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926
+
+iyr:2019
+hcl:#602927 eyr:1967 hgt:170cm
+ecl:grn pid:012533040 byr:1946
+
+pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f
+
+eyr:2029 ecl:blu cid:129 byr:1989
+iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm
+
+hcl:#cfa07d eyr:2025 pid:166559648
+iyr:2011 ecl:brn hgt:59in";
+
+    let passports = parse_passports(input).unwrap();
+
+    for p in &passports {
+        assert_eq!(p.is_valid(), schema.has_required(|name| p.field(name)));
+        assert_eq!(
+            ValidatedPassport::try_from(p.clone()).is_ok(),
+            schema.is_valid(|name| p.field(name))
+        );
+    }
+    assert_eq!(
+        2,
+        passports
+            .iter()
+            .filter(|p| schema.is_valid(|name| p.field(name)))
+            .count()
+    );
+}
+
+#[test]
+fn test_report() {
+    let input = "eyr:1972 cid:100
+hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926
+
+pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f
+
+iyr:2019
+hcl:#602927 hgt:190in
+ecl:grn pid:012533040 byr:1946";
+
+    let passports = parse_passports(input).unwrap();
+    let reports = diagnose(&passports, passport_schema());
+
+    assert_eq!(
+        vec![1, 4, 7],
+        reports.iter().map(|r| r.line).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![true],
+        reports
+            .iter()
+            .filter(|r| r.valid)
+            .map(|r| r.valid)
+            .collect::<Vec<_>>()
+    );
+
+    let mut text = vec![];
+    write_report(&reports[1..], ReportFormat::Text, &mut text).unwrap();
+    assert_eq!(
+        "line 4: valid
+line 7: eyr missing
+line 7: hgt 190in out of range 59..=76
+",
+        String::from_utf8(text).unwrap()
+    );
+
+    let mut json = vec![];
+    write_report(&reports, ReportFormat::Json, &mut json).unwrap();
+    let parsed: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(7, parsed[2]["line"]);
+    assert_eq!("eyr", parsed[2]["missing"][0]);
+    assert_eq!("hgt", parsed[2]["invalid"][0]["field"]);
+
+    let mut csv = vec![];
+    write_report(&reports[2..], ReportFormat::Csv, &mut csv).unwrap();
+    assert_eq!(
+        "line,field,value,reason
+7,eyr,,eyr missing
+7,hgt,190in,hgt 190in out of range 59..=76
+",
+        String::from_utf8(csv).unwrap()
+    );
+}
+
+#[test]
+fn test_validated_passport() {
+    let input = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f
+
+eyr:2029 ecl:blu cid:129 byr:1989
+iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm
+
+iyr:2019
+hcl:#602927 eyr:1967 hgt:170cm
+ecl:grn pid:012533040 byr:1946
+
+hcl:#cfa07d eyr:2025 pid:166559648
+iyr:2011 ecl:brn hgt:59in";
+
+    let mut passports = parse_passports(input).unwrap().into_iter();
+
+    assert_eq!(
+        ValidatedPassport {
+            line: 1,
+            birth_year: 1980,
+            issue_year: 2012,
+            expiration_year: 2030,
+            height: Height {
+                value: 74,
+                unit: HeightUnit::Inches
+            },
+            hair_colour: Rgb {
+                r: 0x62,
+                g: 0x3a,
+                b: 0x2f
+            },
+            eye_colour: EyeColour::Green,
+            passport_id: PassportId(87499704),
+            country_id: None,
+        },
+        ValidatedPassport::try_from(passports.next().unwrap()).unwrap()
+    );
+
+    let second = ValidatedPassport::try_from(passports.next().unwrap()).unwrap();
+    assert_eq!(Some("129".to_owned()), second.country_id);
+    assert_eq!("896056539", second.passport_id.to_string());
+
+    let err = ValidatedPassport::try_from(passports.next().unwrap()).unwrap_err();
+    assert_eq!("line 7: eyr 1967 out of range 2020..=2030", err.to_string());
+
+    let err = ValidatedPassport::try_from(passports.next().unwrap()).unwrap_err();
+    assert_eq!("line 11: byr missing", err.to_string());
+}
+
+#[test]
+fn test_typed_fields() {
+    assert_eq!(
+        "000000001",
+        "000000001".parse::<PassportId>().unwrap().to_string()
+    );
+    assert!("12345678".parse::<PassportId>().is_err());
+    assert_eq!(
+        Height {
+            value: 150,
+            unit: HeightUnit::Centimetres
+        },
+        "150cm".parse().unwrap()
+    );
+    assert_eq!(
+        "abccm is not a number of cm",
+        "abccm".parse::<Height>().unwrap_err().to_string()
+    );
+    assert!("#123abcz".parse::<Rgb>().is_err());
+    assert!("#ABCDEF".parse::<Rgb>().is_err());
+    // ranges are the schema's business, the type only knows the shape
+    assert_eq!(190, "190in".parse::<Height>().unwrap().value);
+    assert_eq!(EyeColour::Hazel, "hzl".parse().unwrap());
+}
+
+#[test]
+fn test_tolerant_batch() {
+    let input = "  \r
+ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\r
+byr:1937 iyr:2017 cid:147 hgt:183cm nick:ferris byr:1940\r
+\t \r
+\r
+
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
+hcl:#cfa07d byr1929
+
+hcl:#ae17e1 iyr:2013 eyr:2024
+ecl:brn pid:760753108 byr:1931 hgt:179cm
+
+
+";
+
+    let records = parse_batch(input);
+    assert_eq!(3, records.len());
+
+    let first = records[0].as_ref().unwrap();
+    assert_eq!(2, first.line);
+    assert_eq!(Some("1937"), first.field("byr"));
+    assert_eq!(Some("ferris"), first.field("nick"));
+    assert_eq!(vec!["byr"], first.duplicates);
+    assert!(passport_schema().diagnose(|n| first.field(n)).is_valid());
+
+    assert_eq!(
+        "line 8: malformed field 'byr1929'",
+        records[1].as_ref().unwrap_err().to_string()
+    );
+
+    let last = records[2].as_ref().unwrap();
+    assert_eq!(10, last.line);
+    assert!(last.is_valid());
+}
+
+#[test]
+fn test_report_duplicates() {
+    let passports = parse_passports(
+        "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f hgt:60in",
+    )
+    .unwrap();
+
+    let mut text = vec![];
+    write_report(
+        &diagnose(&passports, passport_schema()),
+        ReportFormat::Text,
+        &mut text,
+    )
+    .unwrap();
+    assert_eq!(
+        "line 1: valid
+line 1: hgt given more than once
+",
+        String::from_utf8(text).unwrap()
+    );
+}
+
+#[test]
+fn test_malformed_values_do_not_panic() {
+    for hgt in &[
+        "abccm",
+        "cm",
+        "in",
+        "99999999999999999999cm",
+        "-60in",
+        "é",
+        "1é0cm",
+    ] {
+        assert!(
+            ValidatedPassport::try_from(valid_except("hgt", hgt)).is_err(),
+            "{}",
+            hgt
+        );
+    }
+
+    let passports =
+        parse_passports("hgt:abccm byr:20o2 iyr:١٢٣٤ eyr:+202 hcl:#ééé pid:12345678é ecl:x")
+            .unwrap();
+    assert!(ValidatedPassport::try_from(passports[0].clone()).is_err());
+
+    let diagnosis = passport_schema().diagnose(|name| passports[0].field(name));
+    assert_eq!(7, diagnosis.invalid.len());
+}
+
+// the schema and the typed passport on a passport with value in every field
+#[cfg(test)]
+fn check_schema_total(value: &str) -> Result<(), proptest::test_runner::TestCaseError> {
+    let mut p = Passport::new(1);
+    for name in &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"] {
+        p.set(name, value);
+    }
+
+    let diagnosis = passport_schema().diagnose(|name| p.field(name));
+    proptest::prop_assert!(diagnosis.missing.is_empty());
+    let explained = diagnosis
+        .invalid
+        .iter()
+        .all(|problem| problem.reason.starts_with(&problem.field) && problem.value == value);
+    proptest::prop_assert!(explained);
+    let valid = diagnosis.is_valid();
+    proptest::prop_assert_eq!(valid, ValidatedPassport::try_from(p).is_ok());
+
+    Ok(())
+}
+
+// a passport passing every rule except maybe the one given field
+#[cfg(test)]
+fn valid_except(name: &str, value: &str) -> Passport {
+    let mut p = Passport::new(1);
+    p.set(name, value);
+    for (n, v) in &[
+        ("byr", "1980"),
+        ("iyr", "2012"),
+        ("eyr", "2030"),
+        ("hgt", "74in"),
+        ("hcl", "#623a2f"),
+        ("ecl", "grn"),
+        ("pid", "087499704"),
+    ] {
+        if *n != name {
+            p.set(n, v);
+        }
+    }
+
+    p
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn schema_is_total(value in "\\PC*") {
+        check_schema_total(&value)?;
+    }
+
+    #[test]
+    fn schema_is_total_near_valid_input(value in "[+-]?[0-9١٢]{0,12}(cm|in|c|)|#[0-9a-fA-Fé]{0,8}") {
+        check_schema_total(&value)?;
+    }
+
+    #[test]
+    fn valid_values_are_accepted(
+        byr in 1920u16..=2002,
+        cm in 150u32..=193,
+        inches in 59u32..=76,
+        hcl in "#[0-9a-f]{6}",
+        pid in "[0-9]{9}",
+    ) {
+        let fields = [
+            ("byr", byr.to_string()),
+            ("hgt", format!("{}cm", cm)),
+            ("hgt", format!("{}in", inches)),
+            ("hcl", hcl),
+            ("pid", pid),
+        ];
+        for (name, value) in &fields {
+            let accepted = ValidatedPassport::try_from(valid_except(name, value)).is_ok();
+            proptest::prop_assert!(accepted);
+        }
+    }
+
+    #[test]
+    fn schema_agrees_with_typed_passport(
+        byr in "(19|20)[0-9]{2}|[0-9]{3,5}",
+        iyr in "20[0-3][0-9]|[0-9]{3,5}",
+        eyr in "20[0-3][0-9]|[0-9]{3,5}",
+        hgt in "[0-9]{0,4}(cm|in|)",
+        hcl in "#?[0-9a-fA-F]{5,7}",
+        ecl in "amb|blu|brn|gry|grn|hzl|oth|AMB|[a-z]{2,4}",
+        pid in "[0-9]{8,10}",
+    ) {
+        let fields = [
+            ("byr", byr),
+            ("iyr", iyr),
+            ("eyr", eyr),
+            ("hgt", hgt),
+            ("hcl", hcl),
+            ("ecl", ecl),
+            ("pid", pid),
+        ];
+        for (name, value) in &fields {
+            let p = valid_except(name, value);
+            let by_schema = passport_schema().is_valid(|n| p.field(n));
+            let typed = ValidatedPassport::try_from(p).is_ok();
+
+            proptest::prop_assert_eq!(by_schema, typed);
+        }
+    }
+}
+
+#[test]
+fn test_export() {
+    let passports = parse_passports(
+        "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f nick:ferris
+
+iyr:2019 hcl:#602927 eyr:1967 hgt:170cm ecl:grn pid:012533040 byr:1946 byr:1950",
+    )
+    .unwrap();
+
+    let mut jsonl = vec![];
+    export(&passports, None, ExportFormat::JsonLines, &mut jsonl).unwrap();
+    let records: Vec<serde_json::Value> = String::from_utf8(jsonl)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+
+    assert_eq!(2, records.len());
+    assert_eq!(1, records[0]["line"]);
+    assert_eq!("ferris", records[0]["extra"]["nick"]);
+    assert_eq!(true, records[0]["valid"]);
+    assert_eq!("087499704", records[0]["validated"]["passport_id"]);
+    assert_eq!("in", records[0]["validated"]["height"]["unit"]);
+    assert_eq!("green", records[0]["validated"]["eye_colour"]);
+    assert_eq!(0x62, records[0]["validated"]["hair_colour"]["r"]);
+    assert_eq!(serde_json::Value::Null, records[0]["cid"]);
+    assert_eq!(true, records[1]["complete"]);
+    assert_eq!(false, records[1]["valid"]);
+    assert_eq!("byr", records[1]["duplicates"][0]);
+    assert_eq!(
+        "line 4: eyr 1967 out of range 2020..=2030",
+        records[1]["error"]
+    );
+
+    let mut csv = vec![];
+    export(&passports, None, ExportFormat::Csv, &mut csv).unwrap();
+    assert_eq!(
+        "line,byr,iyr,eyr,hgt,hcl,ecl,pid,cid,extra,duplicates,complete,valid,error
+1,1980,2012,2030,74in,#623a2f,grn,087499704,,nick:ferris,,true,true,
+4,1946,2019,1967,170cm,#602927,grn,012533040,,,byr,true,false,line 4: eyr 1967 out of range 2020..=2030
+",
+        String::from_utf8(csv).unwrap()
+    );
+}
+
+#[test]
+fn test_export_with_custom_schema() {
+    let passports = parse_passports(
+        "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f nick:ferris
+
+iyr:2019 eyr:1967 nick:x",
+    )
+    .unwrap();
+    let schema = Schema::from_toml(
+        r#"
+[[fields]]
+name = "nick"
+required = true
+rule = { type = "regex", pattern = "^[a-z]{3,}$" }
+
+[[fields]]
+name = "eyr"
+"#,
+    )
+    .unwrap();
+
+    let mut jsonl = vec![];
+    export(
+        &passports,
+        Some(&schema),
+        ExportFormat::JsonLines,
+        &mut jsonl,
+    )
+    .unwrap();
+    let records: Vec<serde_json::Value> = String::from_utf8(jsonl)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+
+    assert_eq!(true, records[0]["valid"]);
+    assert_eq!(serde_json::Value::Null, records[0]["validated"]);
+    // complete by the custom schema even without the puzzle's fields
+    assert_eq!(true, records[1]["complete"]);
+    assert_eq!(false, records[1]["valid"]);
+
+    let mut csv = vec![];
+    export(&passports, Some(&schema), ExportFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.ends_with(",nick:x,,true,false,\"line 4: nick x doesn't match ^[a-z]{3,}$\"\n"));
+}
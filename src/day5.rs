@@ -1,37 +1,201 @@
-use nom::lib::std::collections::HashSet;
+use anyhow::anyhow;
+use std::convert::TryFrom;
+use std::fmt;
 
-pub fn run() -> Result<(String, String), anyhow::Error> {
-    let input: String = std::fs::read_to_string("res/day5-input")?.parse()?;
+// how many F/B characters pick the row and L/R characters pick the column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Geometry {
+    row_bits: u32,
+    column_bits: u32,
+}
 
-    let seats: Vec<u16> = input.split('\n').map(|s| parse_seat_id(s)).collect();
+impl Geometry {
+    pub fn new(row_bits: u32, column_bits: u32) -> Result<Geometry, anyhow::Error> {
+        if row_bits > 31 || column_bits > 31 {
+            return Err(anyhow!(
+                "a plane can have at most 31 row and 31 column bits, not {} and {}",
+                row_bits,
+                column_bits
+            ));
+        }
 
-    let result_1 = seats.iter().max().unwrap();
-    let result_2 = find_missing(&seats);
+        Ok(Geometry {
+            row_bits,
+            column_bits,
+        })
+    }
 
-    Ok((format!("{}", result_1), format!("{}", result_2)))
+    fn len(&self) -> usize {
+        (self.row_bits + self.column_bits) as usize
+    }
+}
+
+impl Default for Geometry {
+    // the puzzle's plane of 128 rows with 8 seats each
+    fn default() -> Geometry {
+        Geometry {
+            row_bits: 7,
+            column_bits: 3,
+        }
+    }
 }
 
-fn parse_seat_id(id: &str) -> u16 {
-    if id.is_empty() {
-        return 0;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoardingPass {
+    pub row: u32,
+    pub column: u32,
+    geometry: Geometry,
+}
+
+// reads one half of a code, `zero` keeps the lower half and `one` the upper
+fn binary(
+    code: &str,
+    part: &str,
+    offset: usize,
+    zero: char,
+    one: char,
+) -> Result<u32, anyhow::Error> {
+    part.chars().enumerate().try_fold(0, |acc, (i, c)| match c {
+        _ if c == zero => Ok(acc << 1),
+        _ if c == one => Ok(acc << 1 | 1),
+        _ => Err(anyhow!(
+            "boarding pass '{}': '{}' at {} is not {} or {}",
+            code,
+            c,
+            offset + i + 1,
+            zero,
+            one
+        )),
+    })
+}
+
+impl BoardingPass {
+    pub fn new(row: u32, column: u32, geometry: Geometry) -> Result<BoardingPass, anyhow::Error> {
+        if u64::from(row) >= 1 << geometry.row_bits
+            || u64::from(column) >= 1 << geometry.column_bits
+        {
+            return Err(anyhow!(
+                "row {} column {} is outside of a plane with {} rows of {} seats",
+                row,
+                column,
+                1u64 << geometry.row_bits,
+                1u64 << geometry.column_bits
+            ));
+        }
+
+        Ok(BoardingPass {
+            row,
+            column,
+            geometry,
+        })
+    }
+
+    // the inverse of id
+    pub fn from_id(id: u64, geometry: Geometry) -> Result<BoardingPass, anyhow::Error> {
+        let row = u32::try_from(id >> geometry.column_bits)
+            .map_err(|_| anyhow!("seat {} is outside of the plane", id))?;
+        let column = (id & ((1 << geometry.column_bits) - 1)) as u32;
+
+        BoardingPass::new(row, column, geometry).map_err(|e| anyhow!("seat {}: {}", id, e))
+    }
+
+    pub fn decode(code: &str, geometry: Geometry) -> Result<BoardingPass, anyhow::Error> {
+        if code.chars().count() != geometry.len() {
+            return Err(anyhow!(
+                "boarding pass '{}' has {} characters, expected {}",
+                code,
+                code.chars().count(),
+                geometry.len()
+            ));
+        }
+        let split = code
+            .char_indices()
+            .nth(geometry.row_bits as usize)
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| code.len());
+        let (rows, columns) = code.split_at(split);
+
+        Ok(BoardingPass {
+            row: binary(code, rows, 0, 'F', 'B')?,
+            column: binary(code, columns, geometry.row_bits as usize, 'L', 'R')?,
+            geometry,
+        })
     }
 
-    let id = id.replace('F', "0");
-    let id = id.replace('B', "1");
-    let id = id.replace('R', "1");
-    let id = id.replace('L', "0");
+    pub fn id(&self) -> u64 {
+        u64::from(self.row) << self.geometry.column_bits | u64::from(self.column)
+    }
+
+    pub fn encode(&self) -> String {
+        let bits = |value: u32, count: u32, zero: char, one: char| {
+            (0..count)
+                .rev()
+                .map(move |i| if value >> i & 1 == 1 { one } else { zero })
+        };
 
-    u16::from_str_radix(&id, 2).unwrap()
+        bits(self.row, self.geometry.row_bits, 'F', 'B')
+            .chain(bits(self.column, self.geometry.column_bits, 'L', 'R'))
+            .collect()
+    }
 }
 
-fn find_missing(seats: &[u16]) -> u16 {
-    let mut all: HashSet<u16> = (61..995).collect();
+impl fmt::Display for BoardingPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.encode())
+    }
+}
+
+// with encode the free seat's boarding pass is printed too
+pub fn run(geometry: Geometry, encode: bool) -> Result<(String, String), anyhow::Error> {
+    let input: String = std::fs::read_to_string("res/day5-input")?.parse()?;
+
+    let seats = parse_seat_ids(&input, geometry)?;
 
-    for s in seats {
-        all.remove(s);
+    let result_1 = seats
+        .iter()
+        .max()
+        .ok_or_else(|| anyhow!("no boarding passes"))?;
+    let result_2 =
+        find_missing(&seats).ok_or_else(|| anyhow!("no free seat between two taken ones"))?;
+
+    if encode {
+        let pass = BoardingPass::from_id(result_2, geometry)?;
+        println!(
+            "seat {} is row {} column {}, boarding pass {}",
+            result_2, pass.row, pass.column, pass
+        );
     }
 
-    return *all.iter().next().unwrap();
+    Ok((format!("{}", result_1), format!("{}", result_2)))
+}
+
+fn parse_seat_ids(input: &str, geometry: Geometry) -> Result<Vec<u64>, anyhow::Error> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            BoardingPass::decode(line.trim(), geometry)
+                .map(|pass| pass.id())
+                .map_err(|e| anyhow!("line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn parse_seat_id(id: &str) -> u64 {
+    BoardingPass::decode(id, Geometry::default()).unwrap().id()
+}
+
+// our seat is the one missing with both neighbouring ids taken
+fn find_missing(seats: &[u64]) -> Option<u64> {
+    let mut sorted = seats.to_vec();
+    sorted.sort_unstable();
+
+    sorted
+        .windows(2)
+        .find(|w| w[1] == w[0] + 2)
+        .map(|w| w[0] + 1)
 }
 
 #[test]
@@ -40,3 +204,97 @@ fn test_parse() {
     assert_eq!(119, parse_seat_id("FFFBBBFRRR"));
     assert_eq!(820, parse_seat_id("BBFFBBFRLL"));
 }
+
+#[test]
+fn test_decode() {
+    let pass = BoardingPass::decode("FBFBBFFRLR", Geometry::default()).unwrap();
+    assert_eq!((44, 5, 357), (pass.row, pass.column, pass.id()));
+}
+
+#[test]
+fn test_round_trip() {
+    let geometry = Geometry::default();
+    for code in &[
+        "BFFFBBFRRR",
+        "FFFBBBFRRR",
+        "BBFFBBFRLL",
+        "FFFFFFFLLL",
+        "BBBBBBBRRR",
+    ] {
+        assert_eq!(
+            *code,
+            BoardingPass::decode(code, geometry).unwrap().encode()
+        );
+    }
+
+    let pass = BoardingPass::new(70, 7, geometry).unwrap();
+    assert_eq!("BFFFBBFRRR", pass.to_string());
+    assert_eq!(567, pass.id());
+}
+
+#[test]
+fn test_custom_geometry() {
+    let geometry = Geometry::new(4, 2).unwrap();
+    let pass = BoardingPass::decode("BFFBRL", geometry).unwrap();
+    assert_eq!((9, 2, 38), (pass.row, pass.column, pass.id()));
+    assert_eq!(
+        "BFFBRL",
+        BoardingPass::new(9, 2, geometry).unwrap().encode()
+    );
+
+    assert!(BoardingPass::new(16, 0, geometry).is_err());
+    assert!(BoardingPass::new(0, 4, geometry).is_err());
+    assert!(Geometry::new(32, 3).is_err());
+}
+
+#[test]
+fn test_from_id() {
+    let pass = BoardingPass::from_id(357, Geometry::default()).unwrap();
+    assert_eq!((44, 5), (pass.row, pass.column));
+    assert_eq!("FBFBBFFRLR", pass.encode());
+
+    let small = Geometry::new(4, 2).unwrap();
+    assert_eq!("BFFBRL", BoardingPass::from_id(38, small).unwrap().encode());
+    assert_eq!(
+        "seat 64: row 16 column 0 is outside of a plane with 16 rows of 4 seats",
+        BoardingPass::from_id(64, small).unwrap_err().to_string()
+    );
+    assert!(BoardingPass::from_id(u64::MAX, Geometry::default()).is_err());
+}
+
+#[test]
+fn test_bad_passes() {
+    let geometry = Geometry::default();
+    assert_eq!(
+        "boarding pass 'FBFBBFFRL' has 9 characters, expected 10",
+        BoardingPass::decode("FBFBBFFRL", geometry)
+            .unwrap_err()
+            .to_string()
+    );
+    assert_eq!(
+        "boarding pass 'FBFXBFFRLR': 'X' at 4 is not F or B",
+        BoardingPass::decode("FBFXBFFRLR", geometry)
+            .unwrap_err()
+            .to_string()
+    );
+    assert_eq!(
+        "boarding pass 'FBFBBFFRBR': 'B' at 9 is not L or R",
+        BoardingPass::decode("FBFBBFFRBR", geometry)
+            .unwrap_err()
+            .to_string()
+    );
+    assert!(BoardingPass::decode("FBFBBFFRLé", geometry).is_err());
+
+    assert_eq!(
+        "line 2: boarding pass 'FBF' has 3 characters, expected 10",
+        parse_seat_ids("FBFBBFFRLR\nFBF\n", geometry)
+            .unwrap_err()
+            .to_string()
+    );
+}
+
+#[test]
+fn test_find_missing() {
+    assert_eq!(Some(5), find_missing(&[7, 3, 4, 6]));
+    assert_eq!(None, find_missing(&[3, 4, 5]));
+}
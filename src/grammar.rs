@@ -0,0 +1,119 @@
+use anyhow::anyhow;
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{anychar, digit1, space1};
+use nom::combinator::{map, map_res};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, separated_pair};
+use nom::IResult;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    Char(char),
+    Alternatives(Vec<Vec<usize>>),
+}
+
+pub struct Grammar {
+    rules: HashMap<usize, Rule>,
+}
+
+impl Grammar {
+    pub fn set_rule(&mut self, id: usize, rule: Rule) {
+        self.rules.insert(id, rule);
+    }
+
+    pub fn matches_fully(&self, id: usize, input: &str) -> bool {
+        self.matches(id, input).iter().any(|rest| rest.is_empty())
+    }
+
+    // returns every suffix that can remain after matching rule `id` against a prefix of `input`
+    pub fn matches<'a>(&self, id: usize, input: &'a str) -> Vec<&'a str> {
+        match self.rules.get(&id) {
+            Some(Rule::Char(c)) => match input.strip_prefix(*c) {
+                Some(rest) => vec![rest],
+                None => vec![],
+            },
+            Some(Rule::Alternatives(alternatives)) => alternatives
+                .iter()
+                .flat_map(|sequence| self.matches_sequence(sequence, input))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    fn matches_sequence<'a>(&self, sequence: &[usize], input: &'a str) -> Vec<&'a str> {
+        let mut remaining = vec![input];
+        for id in sequence {
+            remaining = remaining
+                .iter()
+                .flat_map(|rest| self.matches(*id, rest))
+                .collect();
+            if remaining.is_empty() {
+                break;
+            }
+        }
+        remaining
+    }
+}
+
+fn from_digit(input: &str) -> Result<usize, std::num::ParseIntError> {
+    input.parse()
+}
+
+fn rule_id(input: &str) -> IResult<&str, usize> {
+    map_res(digit1, from_digit)(input)
+}
+
+fn char_rule(input: &str) -> IResult<&str, Rule> {
+    map(delimited(tag("\""), anychar, tag("\"")), Rule::Char)(input)
+}
+
+fn alternatives_rule(input: &str) -> IResult<&str, Rule> {
+    map(
+        separated_list1(tag(" | "), separated_list1(space1, rule_id)),
+        Rule::Alternatives,
+    )(input)
+}
+
+fn rule_line(input: &str) -> IResult<&str, (usize, Rule)> {
+    separated_pair(rule_id, tag(": "), alt((char_rule, alternatives_rule)))(input)
+}
+
+pub fn parse_rule(line: &str) -> Result<(usize, Rule), anyhow::Error> {
+    let (rest, rule) = rule_line(line).map_err(|e| anyhow!(format!("{:?}", e)))?;
+    if !rest.is_empty() {
+        return Err(anyhow!("trailing data after rule: {}", rest));
+    }
+    Ok(rule)
+}
+
+pub fn parse_grammar(input: &str) -> Result<Grammar, anyhow::Error> {
+    let mut rules = HashMap::new();
+    for line in input.trim().split('\n') {
+        let (id, rule) = parse_rule(line.trim())?;
+        rules.insert(id, rule);
+    }
+
+    Ok(Grammar { rules })
+}
+
+#[test]
+fn test_parse_rule() {
+    assert_eq!((4, Rule::Char('a')), parse_rule("4: \"a\"").unwrap());
+    assert_eq!(
+        (1, Rule::Alternatives(vec![vec![2, 3], vec![3, 2]])),
+        parse_rule("1: 2 3 | 3 2").unwrap()
+    );
+}
+
+#[test]
+fn test_all_suffixes() {
+    let grammar = parse_grammar(
+        "0: 1 | 1 0
+1: \"a\"",
+    )
+    .unwrap();
+
+    assert_eq!(vec!["aa", "a", ""], grammar.matches(0, "aaa"));
+}
@@ -0,0 +1,173 @@
+use anyhow::anyhow;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Grid<T>, anyhow::Error> {
+        let height = rows.len();
+        let width = rows.first().map(|r| r.len()).unwrap_or(0);
+        if rows.iter().any(|r| r.len() != width) {
+            return Err(anyhow!("rows of differing length"));
+        }
+
+        Ok(Grid {
+            width,
+            height,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells.get(y * self.width + x)
+    }
+
+    pub fn row(&self, y: usize) -> Option<Vec<T>> {
+        if y >= self.height {
+            return None;
+        }
+        Some(self.cells[y * self.width..(y + 1) * self.width].to_vec())
+    }
+
+    pub fn column(&self, x: usize) -> Option<Vec<T>> {
+        if x >= self.width {
+            return None;
+        }
+        Some(
+            (0..self.height)
+                .map(|y| self.cells[y * self.width + x].clone())
+                .collect(),
+        )
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    // clockwise quarter turn
+    pub fn rotate(&self) -> Grid<T> {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.width {
+            for x in 0..self.height {
+                cells.push(self.cells[(self.height - 1 - x) * self.width + y].clone());
+            }
+        }
+
+        Grid {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+
+    // mirror around the vertical axis
+    pub fn flip(&self) -> Grid<T> {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.height {
+            for x in (0..self.width).rev() {
+                cells.push(self.cells[y * self.width + x].clone());
+            }
+        }
+
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells,
+        }
+    }
+
+    // all 8 rotations and flips
+    pub fn orientations(&self) -> Vec<Grid<T>> {
+        let mut ret = Vec::with_capacity(8);
+        let mut current = self.clone();
+        for _ in 0..4 {
+            ret.push(current.flip());
+            let next = current.rotate();
+            ret.push(current);
+            current = next;
+        }
+        ret
+    }
+
+    // None when the section doesn't fit inside the grid
+    pub fn sub_grid(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Grid<T>> {
+        if x.checked_add(width)? > self.width || y.checked_add(height)? > self.height {
+            return None;
+        }
+
+        let mut cells = Vec::with_capacity(width * height);
+        for row in y..y + height {
+            cells
+                .extend_from_slice(&self.cells[row * self.width + x..row * self.width + x + width]);
+        }
+
+        Some(Grid {
+            width,
+            height,
+            cells,
+        })
+    }
+}
+
+#[test]
+fn test_rotate() {
+    let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+
+    let expected = Grid::from_rows(vec![vec![4, 1], vec![5, 2], vec![6, 3]]).unwrap();
+    assert_eq!(expected, grid.rotate());
+    assert_eq!(grid, grid.rotate().rotate().rotate().rotate());
+}
+
+#[test]
+fn test_flip() {
+    let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+
+    let expected = Grid::from_rows(vec![vec![3, 2, 1], vec![6, 5, 4]]).unwrap();
+    assert_eq!(expected, grid.flip());
+}
+
+#[test]
+fn test_orientations_distinct() {
+    let grid = Grid::from_rows(vec![vec![1, 2], vec![3, 4]]).unwrap();
+
+    let orientations = grid.orientations();
+    assert_eq!(8, orientations.len());
+    for (i, a) in orientations.iter().enumerate() {
+        for b in orientations.iter().skip(i + 1) {
+            assert_ne!(a, b);
+        }
+    }
+}
+
+#[test]
+fn test_out_of_range() {
+    let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+
+    assert_eq!(Some(vec![4, 5, 6]), grid.row(1));
+    assert_eq!(None, grid.row(2));
+    assert_eq!(Some(vec![3, 6]), grid.column(2));
+    assert_eq!(None, grid.column(3));
+    assert_eq!(None, grid.get(3, 0));
+    assert_eq!(
+        Some(Grid::from_rows(vec![vec![5, 6]]).unwrap()),
+        grid.sub_grid(1, 1, 2, 1)
+    );
+    assert_eq!(None, grid.sub_grid(2, 0, 2, 1));
+    assert_eq!(None, grid.sub_grid(0, 1, 1, 2));
+    assert_eq!(None, grid.sub_grid(usize::MAX, 0, 2, 1));
+}
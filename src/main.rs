@@ -1,9 +1,17 @@
+mod constraints;
 mod day1;
 mod day10;
 mod day11;
 mod day12;
 mod day13;
+mod day19;
 mod day2;
+mod day20;
+mod day21;
+mod day22;
+mod day23;
+mod day24;
+mod day25;
 mod day3;
 mod day4;
 mod day5;
@@ -11,6 +19,13 @@ mod day6;
 mod day7;
 mod day8;
 mod day9;
+mod grammar;
+mod grid;
+mod number_theory;
+mod password_policy;
+mod password_repair;
+mod report;
+mod schema;
 
 use anyhow::anyhow;
 use clap::Clap;
@@ -23,17 +38,79 @@ use clap::Clap;
 struct Opts {
     #[clap(short, long, default_value = "1")]
     day: u8,
+    #[clap(long)]
+    replay: bool,
+    #[clap(long, default_value = "2020")]
+    target: i64,
+    #[clap(long, default_value = "2")]
+    k: usize,
+    #[clap(long, default_value = "first")]
+    pick: day1::Pick,
+    #[clap(long)]
+    all: bool,
+    #[clap(long)]
+    policy: Vec<String>,
+    #[clap(long)]
+    report: Option<report::ReportFormat>,
+    #[clap(long, default_value = "both")]
+    rule: password_repair::Rule,
+    #[clap(long)]
+    fix: bool,
+    #[clap(long)]
+    generate: Option<usize>,
+    #[clap(long, allow_hyphen_values = true, number_of_values = 1)]
+    slope: Vec<day3::Slope>,
+    #[clap(long)]
+    survey: Option<i64>,
+    #[clap(long)]
+    render: bool,
+    #[clap(long)]
+    ppm: Option<String>,
+    #[clap(long, default_value = "wrap")]
+    edge: day3::Edge,
+    #[clap(long, default_value = "error")]
+    edge_y: day3::Edge,
+    #[clap(long)]
+    costs: Option<day3::Costs>,
+    #[clap(long)]
+    schema: Option<String>,
+    #[clap(long)]
+    export: Option<day4::ExportFormat>,
+    #[clap(long, default_value = "7")]
+    row_bits: u32,
+    #[clap(long, default_value = "3")]
+    column_bits: u32,
+    #[clap(long)]
+    encode: bool,
 }
 
 fn main() -> Result<(), anyhow::Error> {
     let opts: Opts = Opts::parse();
 
     let (part1, part2): (String, String) = match opts.day {
-        1 => day1::run()?,
-        2 => day2::run()?,
-        3 => day3::run()?,
-        4 => day4::run()?,
-        5 => day5::run()?,
+        1 => day1::run(opts.target, opts.k, opts.pick, opts.all)?,
+        2 => day2::run(
+            &opts.policy,
+            opts.report,
+            opts.rule,
+            opts.fix,
+            opts.generate,
+        )?,
+        3 => day3::run(&day3::Options {
+            slopes: &opts.slope,
+            survey: opts.survey,
+            render: opts.render,
+            ppm: opts.ppm.as_deref(),
+            edge: opts.edge,
+            edge_y: opts.edge_y,
+            costs: opts.costs,
+            generate: opts.generate,
+        })?,
+        4 => day4::run(opts.schema.as_deref(), opts.report, opts.export)?,
+        5 => day5::run(
+            day5::Geometry::new(opts.row_bits, opts.column_bits)?,
+            opts.encode,
+        )?,
         6 => day6::run()?,
         7 => day7::run()?,
         8 => day8::run()?,
@@ -42,6 +119,13 @@ fn main() -> Result<(), anyhow::Error> {
         11 => day11::run()?,
         12 => day12::run()?,
         13 => day13::run()?,
+        19 => day19::run()?,
+        20 => day20::run()?,
+        21 => day21::run()?,
+        22 => day22::run(opts.replay)?,
+        23 => day23::run()?,
+        24 => day24::run()?,
+        25 => day25::run()?,
         _ => return Err(anyhow!("illegal day number")),
     };
 
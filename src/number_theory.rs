@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+#[allow(clippy::many_single_char_names)]
+pub fn egcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if a == 0 {
+        (b, 0, 1)
+    } else {
+        let (g, x, y) = egcd(b % a, a);
+        (g, y - (b / a) * x, x)
+    }
+}
+
+pub fn mod_inv(x: i64, n: i64) -> Option<i64> {
+    let (g, x, _) = egcd(x, n);
+    if g == 1 {
+        Some((x % n + n) % n)
+    } else {
+        None
+    }
+}
+
+pub fn chinese_remainder(residues: &[i64], modulii: &[i64]) -> Option<i64> {
+    let prod = modulii.iter().product::<i64>();
+
+    let mut sum = 0;
+
+    for (&residue, &modulus) in residues.iter().zip(modulii) {
+        let p = prod / modulus;
+        sum += residue * mod_inv(p, modulus)? * p
+    }
+
+    Some(sum % prod)
+}
+
+// a * b % modulus for a and b already reduced, through i128 so any i64 modulus fits
+fn mul_mod(a: i64, b: i64, modulus: i64) -> i64 {
+    (a as i128 * b as i128 % modulus as i128) as i64
+}
+
+// base^exp (mod modulus) for any modulus of at least 1, None otherwise.
+// A negative exp counts as 0.
+pub fn mod_pow(base: i64, mut exp: i64, modulus: i64) -> Option<i64> {
+    if modulus < 1 {
+        return None;
+    }
+
+    let mut result = 1 % modulus;
+    let mut base = base.rem_euclid(modulus);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+    Some(result)
+}
+
+// baby-step giant-step, finds the smallest x with base^x = target (mod modulus).
+// Works for any modulus of at least 1 but keeps sqrt(modulus) baby steps in memory.
+pub fn discrete_log(base: i64, target: i64, modulus: i64) -> Option<i64> {
+    if modulus < 1 {
+        return None;
+    }
+    let m = (modulus as f64).sqrt().ceil() as i64;
+    let base = base.rem_euclid(modulus);
+    let target = target.rem_euclid(modulus);
+
+    let mut baby_steps = HashMap::new();
+    let mut value = 1 % modulus;
+    for j in 0..m {
+        baby_steps.entry(value).or_insert(j);
+        value = mul_mod(value, base, modulus);
+    }
+
+    let giant_step = mod_inv(mod_pow(base, m, modulus)?, modulus)?;
+    let mut gamma = target;
+    for i in 0..m {
+        if let Some(j) = baby_steps.get(&gamma) {
+            return Some(i * m + j);
+        }
+        gamma = mul_mod(gamma, giant_step, modulus);
+    }
+
+    None
+}
+
+#[test]
+fn test_mod_inv() {
+    assert_eq!(Some(4), mod_inv(3, 11));
+    assert_eq!(None, mod_inv(4, 8));
+}
+
+#[test]
+fn test_mod_pow() {
+    assert_eq!(Some(5764801), mod_pow(7, 8, 20201227));
+    assert_eq!(Some(1), mod_pow(7, 0, 20201227));
+    assert_eq!(Some(0), mod_pow(7, 3, 1));
+    assert_eq!(None, mod_pow(7, 3, 0));
+    assert_eq!(None, mod_pow(7, 3, -5));
+}
+
+#[test]
+fn test_large_modulus() {
+    // a Mersenne prime, squaring anything near it overflows i64
+    let p = (1 << 61) - 1;
+    assert_eq!(Some(1), mod_pow(3, p - 1, p));
+    assert_eq!(Some(p - 1), mod_pow(p - 1, 3, p));
+    assert_eq!(Some(12), discrete_log(2, 4096, 4_000_000_007));
+}
+
+#[test]
+fn test_discrete_log() {
+    assert_eq!(Some(8), discrete_log(7, 5764801, 20201227));
+    assert_eq!(Some(11), discrete_log(7, 17807724, 20201227));
+    assert_eq!(None, discrete_log(2, 3, 7));
+    assert_eq!(None, discrete_log(2, 3, 0));
+}
@@ -0,0 +1,394 @@
+use crate::day2::Password;
+use anyhow::anyhow;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_until};
+use nom::character::complete::{anychar, char, digit1, multispace0, satisfy};
+use nom::combinator::{all_consuming, map, map_res, not, opt};
+use nom::sequence::{delimited, preceded, separated_pair, terminated, tuple};
+use nom::IResult;
+use regex::Regex;
+
+pub const SLED: &str = "count(c) in lo..hi";
+pub const TOBOGGAN: &str = "at(lo) xor at(hi)";
+
+// `lo`/`hi` refer to the numbers of the database entry, a literal number can be used instead
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Lower,
+    Upper,
+    Value(usize),
+}
+
+// `c` is the entry's policy character, `'x'` is a literal one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharRef {
+    Enforced,
+    Literal(char),
+}
+
+#[derive(Debug)]
+pub enum Policy {
+    // inclusive on both ends, like the puzzle's `1-3`
+    Count(CharRef, Bound, Bound),
+    // 1-indexed
+    At(Bound, CharRef),
+    Length(Bound, Bound),
+    Matches(Regex),
+    Not(Box<Policy>),
+    And(Box<Policy>, Box<Policy>),
+    Xor(Box<Policy>, Box<Policy>),
+    Or(Box<Policy>, Box<Policy>),
+}
+
+impl Bound {
+    pub fn resolve(self, password: &Password) -> usize {
+        match self {
+            Bound::Lower => password.lower as usize,
+            Bound::Upper => password.upper as usize,
+            Bound::Value(v) => v,
+        }
+    }
+}
+
+impl CharRef {
+    pub fn resolve(self, password: &Password) -> char {
+        match self {
+            CharRef::Enforced => password.enforced_char,
+            CharRef::Literal(c) => c,
+        }
+    }
+}
+
+impl Policy {
+    pub fn check(&self, password: &Password) -> bool {
+        match self {
+            Policy::Count(c, lower, upper) => {
+                let c = c.resolve(password);
+                let count = password.plain_text.chars().filter(|p| *p == c).count();
+                count >= lower.resolve(password) && count <= upper.resolve(password)
+            }
+            Policy::At(position, c) => {
+                let c = c.resolve(password);
+                position
+                    .resolve(password)
+                    .checked_sub(1)
+                    .and_then(|i| password.plain_text.chars().nth(i))
+                    == Some(c)
+            }
+            Policy::Length(lower, upper) => {
+                let len = password.plain_text.chars().count();
+                len >= lower.resolve(password) && len <= upper.resolve(password)
+            }
+            Policy::Matches(regex) => regex.is_match(&password.plain_text),
+            Policy::Not(p) => !p.check(password),
+            Policy::And(a, b) => a.check(password) && b.check(password),
+            Policy::Xor(a, b) => a.check(password) != b.check(password),
+            Policy::Or(a, b) => a.check(password) || b.check(password),
+        }
+    }
+
+    // like `check`, but also says why, e.g. "found 9 'c', max 8"
+    pub fn explain(&self, password: &Password) -> (bool, String) {
+        match self {
+            Policy::Count(c, lower, upper) => {
+                let c = c.resolve(password);
+                let count = password.plain_text.chars().filter(|p| *p == c).count();
+                let found = format!("found {} '{}'", count, c);
+                within(
+                    count,
+                    lower.resolve(password),
+                    upper.resolve(password),
+                    found,
+                )
+            }
+            Policy::At(position, c) => {
+                let c = c.resolve(password);
+                let position = position.resolve(password);
+                match position
+                    .checked_sub(1)
+                    .and_then(|i| password.plain_text.chars().nth(i))
+                {
+                    Some(found) if found == c => (true, format!("'{}' at {}", c, position)),
+                    Some(found) => (false, format!("'{}' at {}, not '{}'", found, position, c)),
+                    None => (false, format!("nothing at {}, not '{}'", position, c)),
+                }
+            }
+            Policy::Length(lower, upper) => {
+                let len = password.plain_text.chars().count();
+                let found = format!("length {}", len);
+                within(len, lower.resolve(password), upper.resolve(password), found)
+            }
+            Policy::Matches(regex) => {
+                if regex.is_match(&password.plain_text) {
+                    (true, format!("matches /{}/", regex))
+                } else {
+                    (false, format!("doesn't match /{}/", regex))
+                }
+            }
+            Policy::Not(p) => {
+                let (passed, reason) = p.explain(password);
+                (!passed, format!("not ({})", reason))
+            }
+            Policy::And(a, b) => combine(a, b, password, "and", |a, b| a && b),
+            Policy::Xor(a, b) => combine(a, b, password, "xor", |a, b| a != b),
+            Policy::Or(a, b) => combine(a, b, password, "or", |a, b| a || b),
+        }
+    }
+}
+
+fn within(value: usize, lower: usize, upper: usize, found: String) -> (bool, String) {
+    if value < lower {
+        (false, format!("{}, min {}", found, lower))
+    } else if value > upper {
+        (false, format!("{}, max {}", found, upper))
+    } else {
+        (true, format!("{}, within {}..{}", found, lower, upper))
+    }
+}
+
+fn combine(
+    a: &Policy,
+    b: &Policy,
+    password: &Password,
+    operator: &str,
+    op: fn(bool, bool) -> bool,
+) -> (bool, String) {
+    let (passed_a, reason_a) = a.explain(password);
+    let (passed_b, reason_b) = b.explain(password);
+    (
+        op(passed_a, passed_b),
+        format!("({}) {} ({})", reason_a, operator, reason_b),
+    )
+}
+
+fn ws<'a, O, F>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, O>,
+{
+    delimited(multispace0, inner, multispace0)
+}
+
+// an operator word, it can't run into a following name so `andat(2)` is rejected
+fn keyword<'a>(word: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(
+        tag(word),
+        not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+    )
+}
+
+fn from_number(input: &str) -> Result<Bound, std::num::ParseIntError> {
+    Ok(Bound::Value(input.parse()?))
+}
+
+fn from_regex(input: &str) -> Result<Policy, regex::Error> {
+    Ok(Policy::Matches(Regex::new(input)?))
+}
+
+fn bound(input: &str) -> IResult<&str, Bound> {
+    alt((
+        map(tag("lo"), |_| Bound::Lower),
+        map(tag("hi"), |_| Bound::Upper),
+        map_res(digit1, from_number),
+    ))(input)
+}
+
+fn char_ref(input: &str) -> IResult<&str, CharRef> {
+    alt((
+        map(tag("c"), |_| CharRef::Enforced),
+        map(delimited(char('\''), anychar, char('\'')), CharRef::Literal),
+    ))(input)
+}
+
+fn range(input: &str) -> IResult<&str, (Bound, Bound)> {
+    preceded(
+        ws(keyword("in")),
+        separated_pair(ws(bound), tag(".."), ws(bound)),
+    )(input)
+}
+
+fn count(input: &str) -> IResult<&str, Policy> {
+    map(
+        tuple((
+            tag("count"),
+            ws(char('(')),
+            ws(char_ref),
+            ws(char(')')),
+            range,
+        )),
+        |(_, _, c, _, (lower, upper))| Policy::Count(c, lower, upper),
+    )(input)
+}
+
+fn at(input: &str) -> IResult<&str, Policy> {
+    map(
+        tuple((
+            tag("at"),
+            ws(char('(')),
+            ws(bound),
+            ws(char(')')),
+            opt(preceded(ws(tag("==")), ws(char_ref))),
+        )),
+        |(_, _, position, _, c)| Policy::At(position, c.unwrap_or(CharRef::Enforced)),
+    )(input)
+}
+
+fn length(input: &str) -> IResult<&str, Policy> {
+    map(preceded(tag("len"), range), |(lower, upper)| {
+        Policy::Length(lower, upper)
+    })(input)
+}
+
+fn matches(input: &str) -> IResult<&str, Policy> {
+    map_res(
+        delimited(tag("matches(/"), take_until("/)"), tag("/)")),
+        from_regex,
+    )(input)
+}
+
+fn preset(input: &str) -> IResult<&str, Policy> {
+    let (input, name) = alt((tag("sled"), tag("toboggan")))(input)?;
+    let (_, policy) = expression(if name == "sled" { SLED } else { TOBOGGAN })?;
+    Ok((input, policy))
+}
+
+fn unary(input: &str) -> IResult<&str, Policy> {
+    ws(alt((
+        map(preceded(keyword("not"), unary), |p| {
+            Policy::Not(Box::new(p))
+        }),
+        delimited(char('('), expression, char(')')),
+        preset,
+        count,
+        at,
+        length,
+        matches,
+    )))(input)
+}
+
+fn chain<'a, F>(
+    input: &'a str,
+    word: &'static str,
+    operand: F,
+    combine: fn(Box<Policy>, Box<Policy>) -> Policy,
+) -> IResult<&'a str, Policy>
+where
+    F: Fn(&'a str) -> IResult<&'a str, Policy>,
+{
+    let (mut input, mut policy) = operand(input)?;
+    while let Ok((rest, next)) = preceded(keyword(word), &operand)(input) {
+        policy = combine(Box::new(policy), Box::new(next));
+        input = rest;
+    }
+    Ok((input, policy))
+}
+
+fn and(input: &str) -> IResult<&str, Policy> {
+    chain(input, "and", unary, Policy::And)
+}
+
+fn xor(input: &str) -> IResult<&str, Policy> {
+    chain(input, "xor", and, Policy::Xor)
+}
+
+fn expression(input: &str) -> IResult<&str, Policy> {
+    chain(input, "or", xor, Policy::Or)
+}
+
+// the puzzle's rules can be referred to by name as `sled` and `toboggan`
+pub fn compile(input: &str) -> Result<Policy, anyhow::Error> {
+    let (_, policy) = all_consuming(expression)(input)
+        .map_err(|e| anyhow!("invalid policy '{}': {:?}", input, e))?;
+    Ok(policy)
+}
+
+#[cfg(test)]
+fn entry(lower: u32, upper: u32, enforced_char: char, plain_text: &str) -> Password {
+    Password {
+        line: 1,
+        lower,
+        upper,
+        enforced_char,
+        plain_text: plain_text.to_owned(),
+    }
+}
+
+#[test]
+fn test_presets() {
+    let sled = compile("sled").unwrap();
+    let toboggan = compile("toboggan").unwrap();
+
+    assert!(sled.check(&entry(1, 3, 'a', "abcde")));
+    assert!(!sled.check(&entry(1, 3, 'b', "cdefg")));
+    assert!(toboggan.check(&entry(1, 3, 'a', "abcde")));
+    assert!(!toboggan.check(&entry(2, 9, 'c', "ccccccccc")));
+
+    let neither = compile("not (sled or toboggan)").unwrap();
+    assert!(neither.check(&entry(1, 3, 'b', "cdefg")));
+    assert!(!neither.check(&entry(2, 9, 'c', "ccccccccc")));
+}
+
+#[test]
+fn test_literals_and_operators() {
+    let policy = compile("count('x') in 0..0 and not (at(1) == 'a' or at(2))").unwrap();
+
+    assert!(policy.check(&entry(1, 2, 'q', "bcd")));
+    assert!(!policy.check(&entry(1, 2, 'q', "acd")));
+    assert!(!policy.check(&entry(1, 2, 'q', "bqd")));
+    assert!(!policy.check(&entry(1, 2, 'q', "bcx")));
+}
+
+#[test]
+fn test_precedence() {
+    let policy = compile("at(1) or at(2) and at(3)").unwrap();
+
+    assert!(policy.check(&entry(0, 0, 'a', "abb")));
+    assert!(!policy.check(&entry(0, 0, 'a', "bab")));
+}
+
+#[test]
+fn test_length_and_regex() {
+    let policy = compile("len in 8..64 and matches(/[0-9]/)").unwrap();
+
+    assert!(policy.check(&entry(0, 0, 'a', "hunter22")));
+    assert!(!policy.check(&entry(0, 0, 'a', "hunter2")));
+    assert!(!policy.check(&entry(0, 0, 'a', "hunterxx")));
+}
+
+#[test]
+fn test_invalid_policy() {
+    assert!(compile("count(c) in lo..").is_err());
+    assert!(compile("at(1) nand at(2)").is_err());
+    assert!(compile("matches(/[/)").is_err());
+    assert!(compile("at(1) andat(2)").is_err());
+    assert!(compile("at(1) orat(2)").is_err());
+    assert!(compile("notat(1)").is_err());
+    assert!(compile("count(c) in1..3").is_err());
+    assert!(compile("not(at(1))and(at(2))").is_ok());
+}
+
+#[test]
+fn test_explain() {
+    let sled = compile("sled").unwrap();
+    let toboggan = compile("toboggan").unwrap();
+
+    assert_eq!(
+        (false, "found 10 'c', max 9".to_owned()),
+        sled.explain(&entry(2, 9, 'c', "cccccccccc"))
+    );
+    assert_eq!(
+        (false, "found 0 'b', min 1".to_owned()),
+        sled.explain(&entry(1, 3, 'b', "cdefg"))
+    );
+    assert_eq!(
+        (true, "('a' at 1) xor ('c' at 3, not 'a')".to_owned()),
+        toboggan.explain(&entry(1, 3, 'a', "abcde"))
+    );
+    assert_eq!(
+        (false, "('c' at 2) xor ('c' at 9)".to_owned()),
+        toboggan.explain(&entry(2, 9, 'c', "ccccccccc"))
+    );
+    assert_eq!(
+        (false, "nothing at 5, not 'a'".to_owned()),
+        compile("at(5)").unwrap().explain(&entry(1, 3, 'a', "abc"))
+    );
+}
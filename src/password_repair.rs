@@ -0,0 +1,300 @@
+use crate::day2::Password;
+use anyhow::anyhow;
+use rand::Rng;
+use std::str::FromStr;
+
+// the puzzle's two policies, sled being rule 1 and toboggan rule 2
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    Sled,
+    Toboggan,
+    Both,
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Rule, anyhow::Error> {
+        match s {
+            "sled" => Ok(Rule::Sled),
+            "toboggan" => Ok(Rule::Toboggan),
+            "both" => Ok(Rule::Both),
+            _ => Err(anyhow!("rule must be one of sled, toboggan or both")),
+        }
+    }
+}
+
+// positions are 0-based char indices into the text as it is when the edit is applied
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Insert(usize, char),
+    Delete(usize),
+    Substitute(usize, char),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    pub edits: Vec<Edit>,
+    pub text: String,
+}
+
+struct Editor {
+    chars: Vec<char>,
+    edits: Vec<Edit>,
+    c: char,
+    filler: char,
+}
+
+impl Editor {
+    fn new(password: &Password) -> Editor {
+        let c = password.enforced_char;
+        Editor {
+            chars: password.plain_text.chars().collect(),
+            edits: vec![],
+            c,
+            filler: if c == 'a' { 'b' } else { 'a' },
+        }
+    }
+
+    fn substitute(&mut self, i: usize, c: char) {
+        self.chars[i] = c;
+        self.edits.push(Edit::Substitute(i, c));
+    }
+
+    fn delete(&mut self, i: usize) {
+        self.chars.remove(i);
+        self.edits.push(Edit::Delete(i));
+    }
+
+    fn push(&mut self, c: char) {
+        self.edits.push(Edit::Insert(self.chars.len(), c));
+        self.chars.push(c);
+    }
+
+    // 1-indexed like the policies, position 0 never holds anything
+    fn holds_c(&self, position: usize) -> bool {
+        position
+            .checked_sub(1)
+            .and_then(|i| self.chars.get(i))
+            .map(|c| *c == self.c)
+            .unwrap_or(false)
+    }
+
+    fn place_c(&mut self, position: usize) {
+        while self.chars.len() < position - 1 {
+            self.push(self.filler);
+        }
+        if self.chars.len() < position {
+            self.push(self.c);
+        } else if !self.holds_c(position) {
+            self.substitute(position - 1, self.c);
+        }
+    }
+
+    fn clear_c(&mut self, position: usize) {
+        if self.holds_c(position) {
+            self.substitute(position - 1, self.filler);
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.chars.iter().filter(|c| **c == self.c).count()
+    }
+
+    // brings the count of c into lower..=upper, `keep` must stay c and `avoid` must not become c
+    fn fix_count(&mut self, lower: usize, upper: usize, keep: Option<usize>, avoid: Option<usize>) {
+        let protected = |i: usize| Some(i + 1) == keep || Some(i + 1) == avoid;
+
+        while self.count() < lower {
+            match (0..self.chars.len()).find(|i| self.chars[*i] != self.c && !protected(*i)) {
+                Some(i) => self.substitute(i, self.c),
+                None => {
+                    if Some(self.chars.len() + 1) == avoid {
+                        self.push(self.filler);
+                    }
+                    self.push(self.c);
+                }
+            }
+        }
+
+        while self.count() > upper {
+            let i = (0..self.chars.len())
+                .rev()
+                .find(|i| self.chars[*i] == self.c && !protected(*i))
+                .expect("keep holds at most one c and upper is at least one");
+            if keep.is_none() && avoid.is_none() {
+                self.delete(i);
+            } else {
+                self.substitute(i, self.filler);
+            }
+        }
+    }
+
+    fn finish(self) -> Repair {
+        Repair {
+            edits: self.edits,
+            text: self.chars.into_iter().collect(),
+        }
+    }
+}
+
+// Proposes the fewest edits that make the entry comply. Each rule on its own is
+// repaired optimally, for both rules the cheaper of keeping c at `lo` or at `hi`
+// is chosen.
+pub fn repair(password: &Password, rule: Rule) -> Result<Repair, anyhow::Error> {
+    let lower = password.lower as usize;
+    let upper = password.upper as usize;
+    if lower > upper || (rule != Rule::Sled && lower == upper) {
+        return Err(anyhow!("{} can't be satisfied", password));
+    }
+
+    match rule {
+        Rule::Sled => {
+            let mut editor = Editor::new(password);
+            editor.fix_count(lower, upper, None, None);
+            Ok(editor.finish())
+        }
+        Rule::Toboggan => {
+            let mut editor = Editor::new(password);
+            match (editor.holds_c(lower), editor.holds_c(upper)) {
+                (true, true) => editor.clear_c(upper),
+                (false, false) if lower > 0 => editor.place_c(lower),
+                (false, false) => editor.place_c(upper),
+                _ => {}
+            }
+            Ok(editor.finish())
+        }
+        Rule::Both => {
+            let mut best: Option<Repair> = None;
+            for (keep, avoid) in &[(lower, upper), (upper, lower)] {
+                if *keep == 0 {
+                    continue;
+                }
+                let mut editor = Editor::new(password);
+                editor.place_c(*keep);
+                editor.clear_c(*avoid);
+                editor.fix_count(lower, upper, Some(*keep), Some(*avoid));
+                let candidate = editor.finish();
+                if best
+                    .as_ref()
+                    .map(|b| candidate.edits.len() < b.edits.len())
+                    .unwrap_or(true)
+                {
+                    best = Some(candidate);
+                }
+            }
+            best.ok_or_else(|| anyhow!("{} can't be satisfied", password))
+        }
+    }
+}
+
+// a random lowercase entry of about `length` chars that complies with `rule`
+pub fn generate<R: Rng>(rng: &mut R, rule: Rule, length: usize) -> Result<Password, anyhow::Error> {
+    let length = length.max(2) as u32;
+    let lower = rng.gen_range(1..length);
+    let upper = rng.gen_range(lower + 1..=length);
+    let random = Password {
+        line: 0,
+        lower,
+        upper,
+        enforced_char: rng.gen_range(b'a'..=b'z') as char,
+        plain_text: (0..length)
+            .map(|_| rng.gen_range(b'a'..=b'z') as char)
+            .collect(),
+    };
+
+    let repaired = repair(&random, rule)?;
+    Ok(Password {
+        plain_text: repaired.text,
+        ..random
+    })
+}
+
+#[cfg(test)]
+fn entry(lower: u32, upper: u32, enforced_char: char, plain_text: &str) -> Password {
+    Password {
+        line: 1,
+        lower,
+        upper,
+        enforced_char,
+        plain_text: plain_text.to_owned(),
+    }
+}
+
+#[test]
+fn test_repair_sled() {
+    let short = repair(&entry(1, 3, 'b', "cdefg"), Rule::Sled).unwrap();
+    assert_eq!(vec![Edit::Substitute(0, 'b')], short.edits);
+    assert_eq!("bdefg", short.text);
+
+    let long = repair(&entry(2, 9, 'c', "cccccccccccc"), Rule::Sled).unwrap();
+    assert_eq!(3, long.edits.len());
+    assert_eq!("ccccccccc", long.text);
+
+    let valid = repair(&entry(1, 3, 'a', "abcde"), Rule::Sled).unwrap();
+    assert!(valid.edits.is_empty());
+}
+
+#[test]
+fn test_repair_toboggan() {
+    let both = repair(&entry(2, 9, 'c', "ccccccccc"), Rule::Toboggan).unwrap();
+    assert_eq!(vec![Edit::Substitute(8, 'a')], both.edits);
+
+    let short = repair(&entry(4, 6, 'x', "ab"), Rule::Toboggan).unwrap();
+    assert_eq!(
+        vec![Edit::Insert(2, 'a'), Edit::Insert(3, 'x')],
+        short.edits
+    );
+    assert_eq!("abax", short.text);
+
+    assert!(repair(&entry(3, 3, 'x', "abc"), Rule::Toboggan).is_err());
+}
+
+#[test]
+fn test_repair_both() {
+    use crate::password_policy::compile;
+
+    let sled = compile("sled").unwrap();
+    let toboggan = compile("toboggan").unwrap();
+    let cases = vec![
+        entry(2, 9, 'c', "ccccccccc"),
+        entry(1, 3, 'b', "cdefg"),
+        entry(3, 4, 'q', "qqqqqqqq"),
+        entry(5, 6, 'z', "z"),
+        entry(0, 2, 'a', "aaaa"),
+    ];
+
+    for case in cases {
+        let repaired = repair(&case, Rule::Both).unwrap();
+        let fixed = Password {
+            plain_text: repaired.text,
+            ..case
+        };
+        assert!(sled.check(&fixed), "{}", fixed);
+        assert!(toboggan.check(&fixed), "{}", fixed);
+    }
+
+    assert_eq!(
+        1,
+        repair(&entry(1, 3, 'b', "cdefg"), Rule::Both)
+            .unwrap()
+            .edits
+            .len()
+    );
+}
+
+#[test]
+fn test_generate() {
+    use crate::password_policy::compile;
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(2020);
+    let sled = compile("sled").unwrap();
+    let toboggan = compile("toboggan").unwrap();
+
+    for _ in 0..100 {
+        let generated = generate(&mut rng, Rule::Both, 12).unwrap();
+        assert!(sled.check(&generated), "{}", generated);
+        assert!(toboggan.check(&generated), "{}", generated);
+    }
+}
@@ -0,0 +1,29 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+
+// how the days with a --report mode write it out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ReportFormat, anyhow::Error> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            _ => Err(anyhow!("report format must be one of text, json or csv")),
+        }
+    }
+}
+
+#[test]
+fn test_parse_format() {
+    assert_eq!(ReportFormat::Csv, "csv".parse().unwrap());
+    assert!("xml".parse::<ReportFormat>().is_err());
+}
@@ -0,0 +1,350 @@
+use anyhow::anyhow;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Range {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl Range {
+    fn contains(&self, value: i64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RuleSpec {
+    Year(Range),
+    UnitRange { units: BTreeMap<String, Range> },
+    Regex { pattern: String },
+    Enum { values: Vec<String> },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldSpec {
+    name: String,
+    #[serde(default)]
+    required: bool,
+    rule: Option<RuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaSpec {
+    fields: Vec<FieldSpec>,
+}
+
+enum Rule {
+    Year(Range),
+    UnitRange(BTreeMap<String, Range>),
+    Regex(Regex),
+    Enum(Vec<String>),
+}
+
+// a number made of ascii digits only, so signs and spaces are rejected
+fn digits(value: &str) -> Option<i64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn in_range(value: &str, number: i64, range: &Range) -> Result<(), String> {
+    if range.contains(number) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} out of range {}..={}",
+            value, range.min, range.max
+        ))
+    }
+}
+
+impl Rule {
+    // explains why the value breaks the rule, the field name is added by the caller
+    fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            Rule::Year(range) => match digits(value) {
+                Some(year) if value.len() == 4 => in_range(value, year, range),
+                _ => Err(format!("{} is not a 4 digit year", value)),
+            },
+            // the longest unit wins, so 10mm is millimetres even with metres around
+            Rule::UnitRange(units) => {
+                let (unit, range) = units
+                    .iter()
+                    .filter(|(unit, _)| value.ends_with(unit.as_str()))
+                    .max_by_key(|(unit, _)| unit.len())
+                    .ok_or_else(|| {
+                        let names: Vec<&str> = units.keys().map(|u| u.as_str()).collect();
+                        format!("{} needs a unit, one of {}", value, names.join(", "))
+                    })?;
+                match digits(&value[..value.len() - unit.len()]) {
+                    Some(number) => in_range(value, number, range),
+                    None => Err(format!("{} is not a number of {}", value, unit)),
+                }
+            }
+            Rule::Regex(regex) if regex.is_match(value) => Ok(()),
+            Rule::Regex(regex) => Err(format!("{} doesn't match {}", value, regex)),
+            Rule::Enum(values) if values.iter().any(|v| v == value) => Ok(()),
+            Rule::Enum(values) => Err(format!("{} is not one of {}", value, values.join(", "))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Problem {
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Diagnosis {
+    pub missing: Vec<String>,
+    pub invalid: Vec<Problem>,
+}
+
+impl Diagnosis {
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+pub struct Field {
+    pub name: String,
+    pub required: bool,
+    rule: Option<Rule>,
+}
+
+// the fields a kind of document has and what their values have to look like,
+// loaded from TOML or JSON so new document types need no code
+pub struct Schema {
+    pub fields: Vec<Field>,
+}
+
+impl Schema {
+    pub fn from_toml(input: &str) -> Result<Schema, anyhow::Error> {
+        Schema::compile(toml::from_str(input)?)
+    }
+
+    pub fn from_json(input: &str) -> Result<Schema, anyhow::Error> {
+        Schema::compile(serde_json::from_str(input)?)
+    }
+
+    // picks the format from the file extension
+    pub fn load(path: &str) -> Result<Schema, anyhow::Error> {
+        let input = std::fs::read_to_string(path)?;
+        if path.ends_with(".json") {
+            Schema::from_json(&input)
+        } else {
+            Schema::from_toml(&input)
+        }
+    }
+
+    fn compile(spec: SchemaSpec) -> Result<Schema, anyhow::Error> {
+        let mut fields = vec![];
+        for FieldSpec {
+            name,
+            required,
+            rule,
+        } in spec.fields
+        {
+            let rule = match rule {
+                None => None,
+                Some(RuleSpec::Year(range)) => Some(Rule::Year(range)),
+                Some(RuleSpec::UnitRange { units }) => Some(Rule::UnitRange(units)),
+                Some(RuleSpec::Regex { pattern }) => Some(Rule::Regex(
+                    Regex::new(&pattern).map_err(|e| anyhow!("field {}: {}", name, e))?,
+                )),
+                Some(RuleSpec::Enum { values }) => Some(Rule::Enum(values)),
+            };
+            fields.push(Field {
+                name,
+                required,
+                rule,
+            });
+        }
+
+        Ok(Schema { fields })
+    }
+
+    pub fn has_required<'a>(&self, get: impl Fn(&str) -> Option<&'a str>) -> bool {
+        self.fields
+            .iter()
+            .all(|f| !f.required || get(&f.name).is_some())
+    }
+
+    // required fields are present and every present field follows its rule
+    pub fn is_valid<'a>(&self, get: impl Fn(&str) -> Option<&'a str>) -> bool {
+        self.fields.iter().all(|f| match get(&f.name) {
+            Some(value) => f
+                .rule
+                .as_ref()
+                .map(|r| r.check(value).is_ok())
+                .unwrap_or(true),
+            None => !f.required,
+        })
+    }
+
+    // every missing required field and every broken rule, in schema order
+    pub fn diagnose<'a>(&self, get: impl Fn(&str) -> Option<&'a str>) -> Diagnosis {
+        let mut diagnosis = Diagnosis::default();
+        for f in &self.fields {
+            match (get(&f.name), &f.rule) {
+                (None, _) if f.required => diagnosis.missing.push(f.name.clone()),
+                (Some(value), Some(rule)) => {
+                    if let Err(reason) = rule.check(value) {
+                        diagnosis.invalid.push(Problem {
+                            field: f.name.clone(),
+                            value: value.to_owned(),
+                            reason: format!("{} {}", f.name, reason),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        diagnosis
+    }
+}
+
+#[test]
+fn test_rules() {
+    let schema = Schema::from_toml(
+        r#"
+[[fields]]
+name = "year"
+required = true
+rule = { type = "year", min = 1920, max = 2002 }
+
+[[fields]]
+name = "height"
+rule = { type = "unit_range", units = { cm = { min = 150, max = 193 }, in = { min = 59, max = 76 } } }
+"#,
+    )
+    .unwrap();
+
+    let check = |year: Option<&'static str>, height: Option<&'static str>| {
+        schema.is_valid(|name| match name {
+            "year" => year,
+            "height" => height,
+            _ => None,
+        })
+    };
+
+    assert!(check(Some("2002"), None));
+    assert!(!check(None, Some("60in")));
+    assert!(!check(Some("+200"), None));
+    assert!(!check(Some("02002"), None));
+    assert!(check(Some("1920"), Some("190cm")));
+    assert!(!check(Some("1920"), Some("190in")));
+    assert!(!check(Some("1920"), Some("cm")));
+    assert!(!check(Some("1920"), Some("190")));
+}
+
+#[test]
+fn test_json_schema_for_another_document() {
+    let schema = Schema::from_json(
+        r#"{"fields": [
+            {"name": "sku", "required": true, "rule": {"type": "regex", "pattern": "^[A-Z]{3}-[0-9]+$"}},
+            {"name": "size", "rule": {"type": "enum", "values": ["S", "M", "L"]}}
+        ]}"#,
+    )
+    .unwrap();
+
+    let document = |sku, size| {
+        move |name: &str| match name {
+            "sku" => sku,
+            "size" => size,
+            _ => None,
+        }
+    };
+
+    assert!(schema.is_valid(document(Some("ABC-12"), Some("M"))));
+    assert!(!schema.is_valid(document(Some("ABC-12"), Some("XL"))));
+    assert!(schema.has_required(document(Some("abc"), None)));
+    assert!(!schema.has_required(document(None, Some("S"))));
+}
+
+#[test]
+fn test_bad_schema() {
+    let err = Schema::from_json(
+        r#"{"fields": [{"name": "a", "rule": {"type": "regex", "pattern": "("}}]}"#,
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().starts_with("field a: "));
+
+    assert!(Schema::from_json(r#"{"fields": [{"name": "a", "rule": {"type": "date"}}]}"#).is_err());
+
+    // a misspelt key is an error instead of a field that is quietly optional
+    assert!(Schema::from_json(r#"{"fields": [{"name": "a", "requried": true}]}"#).is_err());
+    assert!(Schema::from_toml("feilds = []\n[[fields]]\nname = \"a\"").is_err());
+}
+
+#[test]
+fn test_longest_unit_wins() {
+    let schema = Schema::from_toml(
+        r#"
+[[fields]]
+name = "length"
+rule = { type = "unit_range", units = { m = { min = 1, max = 2 }, mm = { min = 1000, max = 2000 } } }
+"#,
+    )
+    .unwrap();
+
+    let check = |length: &'static str| schema.diagnose(|_| Some(length)).invalid;
+
+    assert!(check("2m").is_empty());
+    assert!(check("1500mm").is_empty());
+    assert_eq!(
+        "length 2mm out of range 1000..=2000",
+        check("2mm")[0].reason
+    );
+    assert_eq!("length 1500m out of range 1..=2", check("1500m")[0].reason);
+}
+
+#[test]
+fn test_diagnose() {
+    let schema = Schema::from_toml(include_str!("../res/day4-schema.toml")).unwrap();
+
+    let diagnosis = schema.diagnose(|name| match name {
+        "hgt" => Some("190in"),
+        "byr" => Some("20x2"),
+        "ecl" => Some("wat"),
+        "hcl" => Some("#123abz"),
+        "iyr" => Some("2012"),
+        "eyr" => Some("2030"),
+        _ => None,
+    });
+
+    assert!(!diagnosis.is_valid());
+    assert_eq!(vec!["pid"], diagnosis.missing);
+    let reasons: Vec<&str> = diagnosis
+        .invalid
+        .iter()
+        .map(|p| p.reason.as_str())
+        .collect();
+    assert_eq!(
+        vec![
+            "byr 20x2 is not a 4 digit year",
+            "hgt 190in out of range 59..=76",
+            "hcl #123abz doesn't match ^#[0-9a-f]{6}$",
+            "ecl wat is not one of amb, blu, brn, gry, grn, hzl, oth",
+        ],
+        reasons
+    );
+    assert_eq!("190in", diagnosis.invalid[1].value);
+
+    let unitless = schema.diagnose(|name| if name == "hgt" { Some("190") } else { None });
+    assert_eq!(
+        "hgt 190 needs a unit, one of cm, in",
+        unitless.invalid[0].reason
+    );
+}